@@ -0,0 +1,79 @@
+// --- Barricades ---
+//
+// Doors and windows on room edges can be boarded up with a barricade kit. A
+// boarded tile blocks movement and sight for the player, but zombies still
+// path into it and hammer away at its HP until the boards give.
+
+use serde::{Deserialize, Serialize};
+
+/// HP of a freshly boarded door or window.
+pub const BARRICADE_HP: i32 = 20;
+/// Turns spent nailing the boards up.
+pub const BARRICADE_TURNS: u32 = 3;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Barricade {
+    pub x: i32,
+    pub y: i32,
+    pub hp: i32,
+}
+
+impl Barricade {
+    pub fn new(x: i32, y: i32) -> Self {
+        Barricade { x, y, hp: BARRICADE_HP }
+    }
+
+    /// Takes a blow; returns true once the boards have given way.
+    pub fn hammer(&mut self, damage: i32) -> bool {
+        self.hp = self.hp.saturating_sub(damage).max(0);
+        self.hp == 0
+    }
+
+    pub fn is_damaged(&self) -> bool {
+        self.hp < BARRICADE_HP
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pathfinding::FlowField;
+    use crate::Map;
+
+    #[test]
+    fn boards_break_after_enough_blows() {
+        let mut b = Barricade::new(0, 0);
+        for _ in 0..BARRICADE_HP / 4 - 1 {
+            assert!(!b.hammer(4));
+        }
+        assert!(b.hammer(4));
+        assert_eq!(b.hp, 0);
+    }
+
+    #[test]
+    fn boarded_tiles_block_the_player_but_not_zombie_paths() {
+        let mut map = Map::from_ascii(&[
+            "#####",
+            "#.+.#",
+            "#####",
+        ]);
+        assert!(map.is_walkable(2, 1));
+        map.board(2, 1);
+        assert!(!map.is_walkable(2, 1));
+        assert!(map.blocks_sight(2, 1));
+
+        // Zombies still walk up to the boards so they can hammer on them.
+        let field = FlowField::from_target(&map, 1, 1, 10);
+        assert_eq!(field.next_step(3, 1, |_, _| false), Some((2, 1)));
+    }
+
+    #[test]
+    fn broken_barricade_is_removed() {
+        let mut map = Map::from_ascii(&[".+."]);
+        map.board(1, 0);
+        assert_eq!(map.hammer_barricade(1, 0, BARRICADE_HP - 1), Some(false));
+        assert_eq!(map.hammer_barricade(1, 0, 1), Some(true));
+        assert!(!map.is_barricaded(1, 0));
+        assert_eq!(map.hammer_barricade(1, 0, 1), None);
+    }
+}
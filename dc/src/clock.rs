@@ -0,0 +1,136 @@
+// --- Clock ---
+//
+// In-game time. Every world turn moves the clock forward a few minutes, and
+// the hour decides the phase of the day: after dark the player sees less,
+// more of the dead show up and the ones already here get restless.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+pub const MINUTES_PER_TURN: u32 = 5;
+const MINUTES_PER_DAY: u32 = 24 * 60;
+/// Runs start at 08:00 on day 1.
+const START_MINUTE: u32 = 8 * 60;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    Dawn,
+    Day,
+    Dusk,
+    Night,
+}
+
+impl Phase {
+    pub fn label(self) -> &'static str {
+        match self {
+            Phase::Dawn => "dawn",
+            Phase::Day => "day",
+            Phase::Dusk => "dusk",
+            Phase::Night => "night",
+        }
+    }
+
+    /// How far the player can see, given the daylight view radius.
+    pub fn view_radius(self, base: i32) -> i32 {
+        match self {
+            Phase::Day => base,
+            Phase::Dawn | Phase::Dusk => base * 2 / 3,
+            Phase::Night => base / 2,
+        }
+    }
+
+    /// Percent chance per turn that a new zombie shambles in.
+    pub fn spawn_chance(self) -> u32 {
+        match self {
+            Phase::Day => 10,
+            Phase::Dawn | Phase::Dusk => 15,
+            Phase::Night => 25,
+        }
+    }
+
+    /// Extra tiles zombies can hear the player from.
+    pub fn hearing_bonus(self) -> i32 {
+        match self {
+            Phase::Day => 0,
+            Phase::Dawn | Phase::Dusk => 1,
+            Phase::Night => 3,
+        }
+    }
+
+    /// Extra energy zombies gain each turn.
+    pub fn speed_bonus(self) -> u8 {
+        match self {
+            Phase::Night => 1,
+            _ => 0,
+        }
+    }
+}
+
+/// Minutes elapsed since midnight before day 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Clock {
+    pub minutes: u32,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock { minutes: START_MINUTE }
+    }
+}
+
+impl Clock {
+    pub fn advance(&mut self, minutes: u32) {
+        self.minutes = self.minutes.saturating_add(minutes);
+    }
+
+    pub fn day(&self) -> u32 {
+        self.minutes / MINUTES_PER_DAY + 1
+    }
+
+    pub fn hour(&self) -> u32 {
+        self.minutes % MINUTES_PER_DAY / 60
+    }
+
+    pub fn minute(&self) -> u32 {
+        self.minutes % 60
+    }
+
+    pub fn phase(&self) -> Phase {
+        match self.hour() {
+            5..=6 => Phase::Dawn,
+            7..=18 => Phase::Day,
+            19..=20 => Phase::Dusk,
+            _ => Phase::Night,
+        }
+    }
+}
+
+impl fmt::Display for Clock {
+    /// Formats as "Day 3, 21:40".
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Day {}, {:02}:{:02}", self.day(), self.hour(), self.minute())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_day_and_time() {
+        let mut clock = Clock::default();
+        assert_eq!(clock.to_string(), "Day 1, 08:00");
+        clock.advance(2 * MINUTES_PER_DAY + 13 * 60 + 40);
+        assert_eq!(clock.to_string(), "Day 3, 21:40");
+        assert_eq!(clock.phase(), Phase::Night);
+    }
+
+    #[test]
+    fn night_shrinks_the_view() {
+        let day = Clock::default();
+        let night = Clock { minutes: 23 * 60 };
+        assert_eq!(day.phase().view_radius(12), 12);
+        assert!(night.phase().view_radius(12) < 12);
+        assert!(night.phase().spawn_chance() > day.phase().spawn_chance());
+    }
+}
@@ -0,0 +1,191 @@
+// --- Crafting ---
+//
+// Recipes are data, not code: they are read from `data/recipes.json` at
+// startup, so a recipe can be added or tweaked without a rebuild. A copy is
+// baked into the binary as well, for when the game runs without its data dir.
+// Crafting consumes the inputs from the backpack, needs any listed tools on
+// hand (without using them up) and takes a number of turns.
+
+use crate::items::{Inventory, ItemId};
+use color_eyre::eyre::{Result, WrapErr};
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const RECIPES_FILE: &str = "data/recipes.json";
+const BUNDLED_RECIPES: &str = include_str!("../data/recipes.json");
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct Ingredient {
+    pub item: ItemId,
+    pub count: u32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Recipe {
+    pub name: String,
+    pub inputs: Vec<Ingredient>,
+    /// Needed on hand but not consumed.
+    #[serde(default)]
+    pub tools: Vec<ItemId>,
+    pub output: Ingredient,
+    pub turns: u32,
+}
+
+impl Recipe {
+    /// Human-readable list of everything still missing, empty when craftable.
+    pub fn missing(&self, inv: &Inventory) -> Vec<String> {
+        let mut missing: Vec<String> = self
+            .inputs
+            .iter()
+            .filter(|ing| inv.count(ing.item) < ing.count)
+            .map(|ing| format!("{} {}", ing.count - inv.count(ing.item), ing.item.name()))
+            .collect();
+        missing.extend(self.tools.iter().filter(|&&tool| !inv.has(tool)).map(|tool| tool.name().to_string()));
+        missing
+    }
+
+    pub fn can_craft(&self, inv: &Inventory) -> bool {
+        self.missing(inv).is_empty()
+    }
+
+    /// Short "2 rags + 1 bottle of whiskey" summary of the inputs.
+    pub fn describe_inputs(&self) -> String {
+        let mut parts: Vec<String> = self.inputs.iter().map(|ing| format!("{} {}", ing.count, ing.item.name())).collect();
+        parts.extend(self.tools.iter().map(|tool| format!("[{}]", tool.name())));
+        parts.join(" + ")
+    }
+}
+
+/// Reads the recipe book from `data/recipes.json`, falling back to the
+/// bundled copy if the file is not there.
+pub fn load_recipes() -> Result<Vec<Recipe>> {
+    load_recipes_from(Path::new(RECIPES_FILE))
+}
+
+fn load_recipes_from(path: &Path) -> Result<Vec<Recipe>> {
+    match fs::read_to_string(path) {
+        Ok(json) => parse_recipes(&json).wrap_err_with(|| format!("{} is malformed", path.display())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            parse_recipes(BUNDLED_RECIPES).wrap_err("the bundled recipe book is malformed")
+        }
+        Err(e) => Err(e).wrap_err_with(|| format!("could not read {}", path.display())),
+    }
+}
+
+fn parse_recipes(json: &str) -> Result<Vec<Recipe>> {
+    Ok(serde_json::from_str(json)?)
+}
+
+/// Consumes the inputs and adds the output. Returns false, leaving the
+/// backpack untouched, if anything is missing.
+pub fn craft(recipe: &Recipe, inv: &mut Inventory) -> bool {
+    if !recipe.can_craft(inv) {
+        return false;
+    }
+    for ing in &recipe.inputs {
+        inv.remove(ing.item, ing.count);
+    }
+    inv.add(recipe.output.item, recipe.output.count);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recipe(output: ItemId) -> Recipe {
+        load_recipes()
+            .unwrap()
+            .into_iter()
+            .find(|r| r.output.item == output)
+            .expect("recipe is in the book")
+    }
+
+    fn backpack(items: &[(ItemId, u32)]) -> Inventory {
+        let mut inv = Inventory::default();
+        for &(id, count) in items {
+            inv.add(id, count);
+        }
+        inv
+    }
+
+    #[test]
+    fn bundled_recipes_parse() {
+        let recipes = load_recipes().unwrap();
+        assert!(recipes.len() >= 4);
+        assert!(recipes.iter().all(|r| !r.inputs.is_empty() && r.turns > 0));
+    }
+
+    #[test]
+    fn recipes_on_disk_replace_the_bundled_book() {
+        let dir = std::env::temp_dir().join(format!("dc-recipes-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("recipes.json");
+        fs::write(&path, r#"[{"name": "Torn rags", "inputs": [{"item": "Bandage", "count": 1}], "output": {"item": "Rags", "count": 2}, "turns": 1}]"#).unwrap();
+
+        let recipes = load_recipes_from(&path).unwrap();
+        assert_eq!(recipes.len(), 1);
+        assert_eq!(recipes[0].output.item, ItemId::Rags);
+
+        fs::write(&path, "[{").unwrap();
+        assert!(load_recipes_from(&path).is_err(), "a broken file is reported, not skipped");
+
+        let bundled = load_recipes_from(&dir.join("missing.json")).unwrap();
+        assert_eq!(bundled.len(), parse_recipes(BUNDLED_RECIPES).unwrap().len());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn molotov_uses_rags_and_alcohol() {
+        let r = recipe(ItemId::Molotov);
+        let mut inv = backpack(&[(ItemId::Rags, 2), (ItemId::Alcohol, 1)]);
+        assert!(craft(&r, &mut inv));
+        assert_eq!(inv.count(ItemId::Molotov), 1);
+        assert_eq!(inv.count(ItemId::Rags), 1);
+        assert!(!inv.has(ItemId::Alcohol));
+    }
+
+    #[test]
+    fn barricade_kit_uses_planks_and_nails() {
+        let r = recipe(ItemId::BarricadeKit);
+        let mut inv = backpack(&[(ItemId::Planks, 2), (ItemId::Nails, 5)]);
+        assert!(craft(&r, &mut inv));
+        assert_eq!(inv.count(ItemId::BarricadeKit), 1);
+        assert!(!inv.has(ItemId::Planks));
+        assert_eq!(inv.count(ItemId::Nails), 1);
+    }
+
+    #[test]
+    fn bandage_uses_two_rags() {
+        let r = recipe(ItemId::Bandage);
+        let mut inv = backpack(&[(ItemId::Rags, 2)]);
+        assert!(craft(&r, &mut inv));
+        assert_eq!(inv.count(ItemId::Bandage), 1);
+        assert!(inv.stacks.iter().all(|s| s.id != ItemId::Rags));
+    }
+
+    #[test]
+    fn first_aid_kit_needs_a_lighter_but_keeps_it() {
+        let r = recipe(ItemId::FirstAidKit);
+        let mut inv = backpack(&[(ItemId::Rags, 1), (ItemId::Alcohol, 1)]);
+        assert_eq!(r.missing(&inv), vec!["lighter".to_string()]);
+        assert!(!craft(&r, &mut inv));
+
+        inv.add(ItemId::Lighter, 1);
+        assert!(craft(&r, &mut inv));
+        assert_eq!(inv.count(ItemId::FirstAidKit), 1);
+        assert!(inv.has(ItemId::Lighter));
+    }
+
+    #[test]
+    fn missing_ingredients_leave_the_backpack_untouched() {
+        let r = recipe(ItemId::BarricadeKit);
+        let mut inv = backpack(&[(ItemId::Planks, 2), (ItemId::Nails, 3)]);
+        assert_eq!(r.missing(&inv), vec!["1 nails".to_string()]);
+        assert!(!craft(&r, &mut inv));
+        assert_eq!(inv.count(ItemId::Planks), 2);
+        assert_eq!(inv.count(ItemId::Nails), 3);
+    }
+}
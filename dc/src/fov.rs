@@ -0,0 +1,256 @@
+// --- Field of View ---
+//
+// Symmetric shadowcasting (after Albert Ford's write-up): the view is scanned
+// one quadrant at a time, row by row outward from the origin, narrowing the
+// visible slope window whenever an opaque tile is hit. Slopes are kept as
+// integer fractions so the symmetry test is exact.
+
+use crate::{Map, Shade};
+use std::collections::HashMap;
+
+#[derive(Clone, Copy)]
+enum Quadrant {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Quadrant {
+    /// Maps a (depth, col) pair in quadrant space to world coordinates.
+    fn transform(self, ox: i32, oy: i32, depth: i32, col: i32) -> (i32, i32) {
+        match self {
+            Quadrant::North => (ox + col, oy - depth),
+            Quadrant::South => (ox + col, oy + depth),
+            Quadrant::East => (ox + depth, oy + col),
+            Quadrant::West => (ox - depth, oy + col),
+        }
+    }
+}
+
+/// A slope `num / den` with `den > 0`.
+#[derive(Clone, Copy)]
+struct Slope {
+    num: i32,
+    den: i32,
+}
+
+impl Slope {
+    fn new(num: i32, den: i32) -> Self {
+        Slope { num, den }
+    }
+
+    /// Slope through the leading edge of the tile at `col` on row `depth`.
+    fn of_tile(depth: i32, col: i32) -> Self {
+        Slope::new(2 * col - 1, 2 * depth)
+    }
+}
+
+struct Row {
+    depth: i32,
+    start: Slope,
+    end: Slope,
+}
+
+impl Row {
+    /// First column on this row, rounding ties up.
+    fn min_col(&self) -> i32 {
+        (2 * self.depth * self.start.num + self.start.den).div_euclid(2 * self.start.den)
+    }
+
+    /// Last column on this row, rounding ties down.
+    fn max_col(&self) -> i32 {
+        -(self.end.den - 2 * self.depth * self.end.num).div_euclid(2 * self.end.den)
+    }
+
+    /// A floor tile is only revealed if its centre lies inside the window,
+    /// which is what makes the algorithm symmetric.
+    fn is_symmetric(&self, col: i32) -> bool {
+        col * self.start.den >= self.depth * self.start.num && col * self.end.den <= self.depth * self.end.num
+    }
+
+    fn next(&self) -> Row {
+        Row { depth: self.depth + 1, start: self.start, end: self.end }
+    }
+}
+
+fn shade_for(dx: i32, dy: i32, radius: i32) -> Shade {
+    let dist = ((dx * dx + dy * dy) as f32).sqrt();
+    if dist > (radius as f32) * 0.7f32 {
+        Shade::Dim
+    } else if dist > (radius as f32) * 0.3f32 {
+        Shade::Lit
+    } else {
+        Shade::Bright
+    }
+}
+
+pub fn compute(map: &Map, px: i32, py: i32, radius: i32) -> HashMap<(i32, i32), Shade> {
+    let mut visible: HashMap<(i32, i32), Shade> = HashMap::new();
+    if !map.in_bounds(px, py) {
+        return visible;
+    }
+    visible.insert((px, py), Shade::Bright);
+
+    // Out-of-bounds tiles behave like walls that are never shown.
+    let opaque = |x: i32, y: i32| !map.in_bounds(x, y) || map.blocks_sight(x, y);
+
+    for quadrant in [Quadrant::North, Quadrant::East, Quadrant::South, Quadrant::West] {
+        let mut rows = vec![Row { depth: 1, start: Slope::new(-1, 1), end: Slope::new(1, 1) }];
+
+        while let Some(mut row) = rows.pop() {
+            if row.depth > radius {
+                continue;
+            }
+            let mut prev_opaque: Option<bool> = None;
+
+            for col in row.min_col()..=row.max_col() {
+                let (tx, ty) = quadrant.transform(px, py, row.depth, col);
+                let is_opaque = opaque(tx, ty);
+                let (dx, dy) = (tx - px, ty - py);
+
+                if (is_opaque || row.is_symmetric(col)) && dx * dx + dy * dy <= radius * radius && map.in_bounds(tx, ty) {
+                    visible.insert((tx, ty), shade_for(dx, dy, radius));
+                }
+
+                if prev_opaque == Some(true) && !is_opaque {
+                    row.start = Slope::of_tile(row.depth, col);
+                }
+                if prev_opaque == Some(false) && is_opaque {
+                    let mut next = row.next();
+                    next.end = Slope::of_tile(row.depth, col);
+                    rows.push(next);
+                }
+                prev_opaque = Some(is_opaque);
+            }
+
+            if prev_opaque == Some(false) {
+                rows.push(row.next());
+            }
+        }
+    }
+
+    visible
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn open_room_is_fully_visible() {
+        let map = Map::from_ascii(&[
+            "#######",
+            "#.....#",
+            "#.....#",
+            "#.....#",
+            "#######",
+        ]);
+        let fov = compute(&map, 3, 2, 10);
+        for y in 0..5 {
+            for x in 0..7 {
+                assert!(fov.contains_key(&(x, y)), "({}, {}) should be visible", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn origin_is_bright() {
+        let map = Map::from_ascii(&["...", "...", "..."]);
+        let fov = compute(&map, 1, 1, 5);
+        assert_eq!(fov.get(&(1, 1)), Some(&Shade::Bright));
+    }
+
+    #[test]
+    fn wall_hides_tiles_behind_it() {
+        let map = Map::from_ascii(&[
+            ".........",
+            "....#....",
+            ".........",
+        ]);
+        let fov = compute(&map, 1, 1, 10);
+        assert!(fov.contains_key(&(4, 1)), "the wall itself is seen");
+        assert!(!fov.contains_key(&(5, 1)), "tile directly behind the wall is hidden");
+        assert!(!fov.contains_key(&(8, 1)), "the shadow extends to the edge");
+    }
+
+    #[test]
+    fn building_interior_is_hidden_from_outside() {
+        let map = Map::from_ascii(&[
+            "..........",
+            "...####...",
+            "...#..#...",
+            "...####...",
+            "..........",
+        ]);
+        let fov = compute(&map, 0, 2, 20);
+        assert!(fov.contains_key(&(3, 2)));
+        assert!(!fov.contains_key(&(4, 2)));
+        assert!(!fov.contains_key(&(5, 2)));
+    }
+
+    #[test]
+    fn cars_and_buildings_block_sight() {
+        let map = Map::from_ascii(&[
+            ".......",
+            "..C....",
+            ".......",
+            "..B....",
+            ".......",
+        ]);
+        let fov = compute(&map, 0, 1, 10);
+        assert!(fov.contains_key(&(2, 1)));
+        assert!(!fov.contains_key(&(3, 1)));
+
+        let fov = compute(&map, 0, 3, 10);
+        assert!(fov.contains_key(&(2, 3)));
+        assert!(!fov.contains_key(&(3, 3)));
+    }
+
+    #[test]
+    fn radius_limits_sight() {
+        let map = Map::from_ascii(&["..........."]);
+        let fov = compute(&map, 0, 0, 4);
+        assert!(fov.contains_key(&(4, 0)));
+        assert!(!fov.contains_key(&(5, 0)));
+    }
+
+    #[test]
+    fn shade_falls_off_with_distance() {
+        let map = Map::from_ascii(&["..........."]);
+        let fov = compute(&map, 0, 0, 10);
+        assert_eq!(fov.get(&(2, 0)), Some(&Shade::Bright));
+        assert_eq!(fov.get(&(5, 0)), Some(&Shade::Lit));
+        assert_eq!(fov.get(&(9, 0)), Some(&Shade::Dim));
+    }
+
+    #[test]
+    fn floor_visibility_is_symmetric() {
+        let map = Map::from_ascii(&[
+            "############",
+            "#....#.....#",
+            "#.#......#.#",
+            "#....C.....#",
+            "#.##...#...#",
+            "#......B...#",
+            "#..#.......#",
+            "############",
+        ]);
+        let floors: Vec<(i32, i32)> = (0..map.height as i32)
+            .flat_map(|y| (0..map.width as i32).map(move |x| (x, y)))
+            .filter(|&(x, y)| !map.blocks_sight(x, y))
+            .collect();
+
+        let views: Vec<_> = floors.iter().map(|&(x, y)| compute(&map, x, y, 20)).collect();
+
+        for (i, &(ax, ay)) in floors.iter().enumerate() {
+            for (j, &(bx, by)) in floors.iter().enumerate() {
+                assert_eq!(
+                    views[i].contains_key(&(bx, by)),
+                    views[j].contains_key(&(ax, ay)),
+                    "asymmetric between ({}, {}) and ({}, {})",
+                    ax, ay, bx, by
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,173 @@
+// --- Run History ---
+//
+// Every finished run is appended to a JSON file in the user's data directory,
+// so the Hall of Survivors can rank them across sessions. Like saves, the
+// file carries a format version and an unreadable one is reported, never
+// silently overwritten.
+
+use crate::mapgen::MapKind;
+use crate::outcome::Outcome;
+use crate::stats::RunStats;
+use crate::GameState;
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Result,
+};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Bump whenever `RunRecord` or anything it contains changes shape.
+pub const HISTORY_VERSION: u32 = 1;
+const HISTORY_FILE: &str = "history.json";
+const APP_DIR: &str = "zomboid-mania";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub seed: u64,
+    pub map_kind: MapKind,
+    /// Seconds since the Unix epoch when the run ended.
+    pub ended_at: u64,
+    pub outcome: Outcome,
+    pub score: u32,
+    pub turns: u32,
+    pub notes: u32,
+    pub stats: RunStats,
+}
+
+impl RunRecord {
+    pub fn new(state: &GameState, ended_at: u64) -> Self {
+        let outcome = state.outcome.unwrap_or(Outcome::Quit);
+        RunRecord {
+            seed: state.seed,
+            map_kind: state.map_kind,
+            ended_at,
+            outcome,
+            score: state.stats.score(state.move_counter, state.notes_collected, outcome),
+            turns: state.move_counter,
+            notes: state.notes_collected,
+            stats: state.stats.clone(),
+        }
+    }
+
+    /// The day the run ended, as YYYY-MM-DD (UTC).
+    pub fn date(&self) -> String {
+        // Days-to-civil conversion from Howard Hinnant's date algorithms.
+        let z = (self.ended_at / 86_400) as i64 + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + i64::from(month <= 2);
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct History {
+    version: u32,
+    pub runs: Vec<RunRecord>,
+}
+
+impl History {
+    /// The best `n` runs: highest score first, longest survival breaking ties.
+    pub fn top(&self, n: usize) -> Vec<RunRecord> {
+        let mut runs = self.runs.clone();
+        runs.sort_by(|a, b| b.score.cmp(&a.score).then(b.turns.cmp(&a.turns)));
+        runs.truncate(n);
+        runs
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// `$XDG_DATA_HOME/zomboid-mania`, else `~/.local/share/zomboid-mania`, else
+/// `%APPDATA%\zomboid-mania`; the working directory if none of those is set.
+pub fn data_dir() -> PathBuf {
+    let env_dir = |var: &str| std::env::var_os(var).filter(|v| !v.is_empty()).map(PathBuf::from);
+    env_dir("XDG_DATA_HOME")
+        .or_else(|| env_dir("HOME").map(|home| home.join(".local").join("share")))
+        .or_else(|| env_dir("APPDATA"))
+        .map_or_else(|| PathBuf::from("."), |base| base.join(APP_DIR))
+}
+
+pub fn history_path() -> PathBuf {
+    data_dir().join(HISTORY_FILE)
+}
+
+pub fn load() -> Result<History> {
+    load_from(&history_path())
+}
+
+/// Appends a finished run to the history file.
+pub fn record(run: RunRecord) -> Result<()> {
+    record_at(&history_path(), run)
+}
+
+fn load_from(path: &Path) -> Result<History> {
+    if !path.exists() {
+        return Ok(History { version: HISTORY_VERSION, runs: vec![] });
+    }
+    let text = fs::read_to_string(path).wrap_err_with(|| format!("could not read {}", path.display()))?;
+    let value: serde_json::Value = serde_json::from_str(&text).wrap_err("run history is corrupt")?;
+    match value.get("version").and_then(|v| v.as_u64()) {
+        Some(v) if v == HISTORY_VERSION as u64 => {}
+        Some(v) => return Err(eyre!("run history is version {}, this build reads version {}", v, HISTORY_VERSION)),
+        None => return Err(eyre!("run history has no format version")),
+    }
+    serde_json::from_value(value).wrap_err("run history does not match the current format")
+}
+
+fn record_at(path: &Path, run: RunRecord) -> Result<()> {
+    let mut history = load_from(path)?;
+    history.runs.push(run);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).wrap_err_with(|| format!("could not create {}", dir.display()))?;
+    }
+    let json = serde_json::to_string_pretty(&history)?;
+    fs::write(path, json).wrap_err_with(|| format!("could not write {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(seed: u64, score: u32, turns: u32) -> RunRecord {
+        let mut state = GameState::new(seed);
+        state.move_counter = turns;
+        let mut record = RunRecord::new(&state, 0);
+        record.score = score;
+        record
+    }
+
+    #[test]
+    fn runs_accumulate_across_loads_and_rank_by_score() {
+        let path = std::env::temp_dir().join(format!("dc-history-test-{}", std::process::id())).join(HISTORY_FILE);
+        let _ = fs::remove_file(&path);
+
+        record_at(&path, run(1, 40, 100)).unwrap();
+        record_at(&path, run(2, 900, 50)).unwrap();
+        record_at(&path, run(3, 40, 300)).unwrap();
+
+        let history = load_from(&path).unwrap();
+        assert_eq!(history.runs.len(), 3);
+        let seeds: Vec<u64> = history.top(2).iter().map(|r| r.seed).collect();
+        assert_eq!(seeds, vec![2, 3]);
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn dates_are_calendar_days() {
+        let mut record = run(0, 0, 0);
+        assert_eq!(record.date(), "1970-01-01");
+        record.ended_at = 1_709_210_096; // 2024-02-29 12:34:56 UTC
+        assert_eq!(record.date(), "2024-02-29");
+    }
+}
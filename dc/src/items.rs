@@ -0,0 +1,298 @@
+// --- Items ---
+//
+// Every item the player can carry is an `ItemId` with a static definition in
+// the registry below. The backpack holds stacks of ids; anything that varies
+// per stack (count, remaining durability) lives on the stack itself.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ItemId {
+    RustyKnife,
+    BaseballBat,
+    Crowbar,
+    FireAxe,
+    Pistol,
+    PistolAmmo,
+    Shotgun,
+    ShotgunShells,
+    Lighter,
+    Rags,
+    EnergyBar,
+    MoldyBerry,
+    DampLeaf,
+    WaterBottle,
+    CanOfBeans,
+    Bandage,
+    FirstAidKit,
+    Alcohol,
+    Molotov,
+    Planks,
+    Nails,
+    BarricadeKit,
+    EmptyBottle,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ItemCategory {
+    Weapon,
+    Ammo,
+    Food,
+    Drink,
+    Medical,
+    Material,
+    Tool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UseEffect {
+    None,
+    Hunger(i32),
+    Thirst(i32),
+    Heal(i32),
+    /// Lit and thrown at the nearest visible zombie, burning everything next to it.
+    Firebomb(i32),
+}
+
+pub struct ItemDef {
+    pub id: ItemId,
+    pub name: &'static str,
+    pub category: ItemCategory,
+    /// Weight of a single item, in kilograms.
+    pub weight: f32,
+    /// Uses before the item breaks; `None` for items that never wear out.
+    pub durability: Option<u32>,
+    pub effect: UseEffect,
+    pub stackable: bool,
+}
+
+static REGISTRY: &[ItemDef] = &[
+    ItemDef { id: ItemId::RustyKnife, name: "rusty knife", category: ItemCategory::Weapon, weight: 0.3, durability: Some(60), effect: UseEffect::None, stackable: false },
+    ItemDef { id: ItemId::BaseballBat, name: "baseball bat", category: ItemCategory::Weapon, weight: 1.0, durability: Some(40), effect: UseEffect::None, stackable: false },
+    ItemDef { id: ItemId::Crowbar, name: "crowbar", category: ItemCategory::Weapon, weight: 1.5, durability: Some(80), effect: UseEffect::None, stackable: false },
+    ItemDef { id: ItemId::FireAxe, name: "fire axe", category: ItemCategory::Weapon, weight: 2.5, durability: Some(30), effect: UseEffect::None, stackable: false },
+    ItemDef { id: ItemId::Pistol, name: "pistol", category: ItemCategory::Weapon, weight: 1.0, durability: Some(200), effect: UseEffect::None, stackable: false },
+    ItemDef { id: ItemId::PistolAmmo, name: "9mm rounds", category: ItemCategory::Ammo, weight: 0.01, durability: None, effect: UseEffect::None, stackable: true },
+    ItemDef { id: ItemId::Shotgun, name: "shotgun", category: ItemCategory::Weapon, weight: 3.2, durability: Some(120), effect: UseEffect::None, stackable: false },
+    ItemDef { id: ItemId::ShotgunShells, name: "shotgun shells", category: ItemCategory::Ammo, weight: 0.04, durability: None, effect: UseEffect::None, stackable: true },
+    ItemDef { id: ItemId::Lighter, name: "lighter", category: ItemCategory::Tool, weight: 0.05, durability: None, effect: UseEffect::None, stackable: false },
+    ItemDef { id: ItemId::Rags, name: "rags", category: ItemCategory::Material, weight: 0.1, durability: None, effect: UseEffect::None, stackable: true },
+    ItemDef { id: ItemId::EnergyBar, name: "energy bar", category: ItemCategory::Food, weight: 0.1, durability: None, effect: UseEffect::Hunger(10), stackable: true },
+    ItemDef { id: ItemId::MoldyBerry, name: "moldy berry", category: ItemCategory::Food, weight: 0.02, durability: None, effect: UseEffect::Hunger(5), stackable: true },
+    ItemDef { id: ItemId::DampLeaf, name: "damp leaf", category: ItemCategory::Drink, weight: 0.01, durability: None, effect: UseEffect::Thirst(5), stackable: true },
+    ItemDef { id: ItemId::WaterBottle, name: "water bottle", category: ItemCategory::Drink, weight: 0.5, durability: None, effect: UseEffect::Thirst(20), stackable: true },
+    ItemDef { id: ItemId::CanOfBeans, name: "can of beans", category: ItemCategory::Food, weight: 0.4, durability: None, effect: UseEffect::Hunger(20), stackable: true },
+    ItemDef { id: ItemId::Bandage, name: "bandage", category: ItemCategory::Medical, weight: 0.05, durability: None, effect: UseEffect::Heal(5), stackable: true },
+    ItemDef { id: ItemId::FirstAidKit, name: "first aid kit", category: ItemCategory::Medical, weight: 0.6, durability: None, effect: UseEffect::Heal(15), stackable: true },
+    ItemDef { id: ItemId::Alcohol, name: "bottle of whiskey", category: ItemCategory::Material, weight: 0.8, durability: None, effect: UseEffect::None, stackable: true },
+    ItemDef { id: ItemId::Molotov, name: "molotov", category: ItemCategory::Tool, weight: 0.9, durability: None, effect: UseEffect::Firebomb(8), stackable: true },
+    ItemDef { id: ItemId::Planks, name: "planks", category: ItemCategory::Material, weight: 1.5, durability: None, effect: UseEffect::None, stackable: true },
+    ItemDef { id: ItemId::Nails, name: "nails", category: ItemCategory::Material, weight: 0.02, durability: None, effect: UseEffect::None, stackable: true },
+    ItemDef { id: ItemId::BarricadeKit, name: "barricade kit", category: ItemCategory::Material, weight: 3.0, durability: None, effect: UseEffect::None, stackable: true },
+    ItemDef { id: ItemId::EmptyBottle, name: "empty bottle", category: ItemCategory::Tool, weight: 0.1, durability: None, effect: UseEffect::None, stackable: true },
+];
+
+impl ItemId {
+    pub fn def(self) -> &'static ItemDef {
+        REGISTRY
+            .iter()
+            .find(|d| d.id == self)
+            .expect("every ItemId has a registry entry")
+    }
+
+    pub fn name(self) -> &'static str {
+        self.def().name
+    }
+
+    /// What is left in hand once the item has been used up, e.g. the bottle
+    /// after the water is gone.
+    pub fn leaves_behind(self) -> Option<ItemId> {
+        match self {
+            ItemId::WaterBottle => Some(ItemId::EmptyBottle),
+            _ => None,
+        }
+    }
+
+    /// Turns spent fumbling to eat, drink or apply the item; `None` if it
+    /// cannot be used directly.
+    pub fn use_turns(self) -> Option<u32> {
+        let def = self.def();
+        match (def.category, def.effect) {
+            (_, UseEffect::None) => None,
+            (_, UseEffect::Firebomb(_)) => Some(1),
+            (ItemCategory::Food | ItemCategory::Drink, _) => Some(1),
+            (ItemCategory::Medical, _) => Some(2),
+            _ => None,
+        }
+    }
+}
+
+impl ItemCategory {
+    pub fn label(self) -> &'static str {
+        match self {
+            ItemCategory::Weapon => "weapon",
+            ItemCategory::Ammo => "ammo",
+            ItemCategory::Food => "food",
+            ItemCategory::Drink => "drink",
+            ItemCategory::Medical => "medical",
+            ItemCategory::Material => "material",
+            ItemCategory::Tool => "tool",
+        }
+    }
+}
+
+impl UseEffect {
+    pub fn describe(self) -> String {
+        match self {
+            UseEffect::None => "no direct use".to_string(),
+            UseEffect::Hunger(n) => format!("+{} Hunger", n),
+            UseEffect::Thirst(n) => format!("+{} Thirst", n),
+            UseEffect::Heal(n) => format!("+{} Health", n),
+            UseEffect::Firebomb(n) => format!("{} fire damage around the target (needs a lighter)", n),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct ItemStack {
+    pub id: ItemId,
+    pub count: u32,
+    /// Remaining uses for items with durability.
+    pub durability: Option<u32>,
+}
+
+impl ItemStack {
+    pub fn new(id: ItemId, count: u32) -> Self {
+        ItemStack { id, count, durability: id.def().durability }
+    }
+
+    pub fn weight(&self) -> f32 {
+        self.id.def().weight * self.count as f32
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Inventory {
+    pub stacks: Vec<ItemStack>,
+}
+
+impl Inventory {
+    /// Adds `count` of an item, merging into an existing stack when stackable.
+    pub fn add(&mut self, id: ItemId, count: u32) {
+        if id.def().stackable {
+            if let Some(stack) = self.stacks.iter_mut().find(|s| s.id == id) {
+                stack.count += count;
+                return;
+            }
+            self.stacks.push(ItemStack::new(id, count));
+        } else {
+            for _ in 0..count {
+                self.stacks.push(ItemStack::new(id, 1));
+            }
+        }
+    }
+
+    /// Removes `count` of an item; returns false (and removes nothing) if
+    /// there are not enough.
+    pub fn remove(&mut self, id: ItemId, count: u32) -> bool {
+        if self.count(id) < count {
+            return false;
+        }
+        let mut left = count;
+        while left > 0 {
+            let idx = self.stacks.iter().rposition(|s| s.id == id).expect("count checked above");
+            let take = left.min(self.stacks[idx].count);
+            self.stacks[idx].count -= take;
+            left -= take;
+            if self.stacks[idx].count == 0 {
+                self.stacks.remove(idx);
+            }
+        }
+        true
+    }
+
+    /// Splits `count` items off the stack at `index`, dropping the stack if it
+    /// empties. Keeps the per-stack durability of what was taken.
+    pub fn take_from(&mut self, index: usize, count: u32) -> Option<ItemStack> {
+        let stack = self.stacks.get_mut(index)?;
+        let take = count.min(stack.count);
+        let taken = ItemStack { id: stack.id, count: take, durability: stack.durability };
+        stack.count -= take;
+        if stack.count == 0 {
+            self.stacks.remove(index);
+        }
+        Some(taken)
+    }
+
+    /// Puts a whole stack back, merging it when the item stacks.
+    pub fn add_stack(&mut self, stack: ItemStack) {
+        if stack.id.def().stackable {
+            self.add(stack.id, stack.count);
+        } else {
+            self.stacks.push(stack);
+        }
+    }
+
+    pub fn count(&self, id: ItemId) -> u32 {
+        self.stacks.iter().filter(|s| s.id == id).map(|s| s.count).sum()
+    }
+
+    pub fn has(&self, id: ItemId) -> bool {
+        self.count(id) > 0
+    }
+
+    pub fn total_weight(&self) -> f32 {
+        self.stacks.iter().map(|s| s.weight()).sum()
+    }
+}
+
+/// An item stack lying on the map, e.g. something the player dropped.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct GroundItem {
+    pub x: i32,
+    pub y: i32,
+    pub stack: ItemStack,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stackable_items_merge() {
+        let mut inv = Inventory::default();
+        inv.add(ItemId::Rags, 2);
+        inv.add(ItemId::Rags, 3);
+        assert_eq!(inv.stacks.len(), 1);
+        assert_eq!(inv.count(ItemId::Rags), 5);
+    }
+
+    #[test]
+    fn unstackable_items_get_their_own_stack() {
+        let mut inv = Inventory::default();
+        inv.add(ItemId::RustyKnife, 2);
+        assert_eq!(inv.stacks.len(), 2);
+        assert_eq!(inv.stacks[0].durability, Some(60));
+    }
+
+    #[test]
+    fn remove_fails_without_enough_items() {
+        let mut inv = Inventory::default();
+        inv.add(ItemId::PistolAmmo, 2);
+        assert!(!inv.remove(ItemId::PistolAmmo, 3));
+        assert_eq!(inv.count(ItemId::PistolAmmo), 2);
+        assert!(inv.remove(ItemId::PistolAmmo, 2));
+        assert!(inv.stacks.is_empty());
+    }
+
+    #[test]
+    fn take_from_splits_a_stack() {
+        let mut inv = Inventory::default();
+        inv.add(ItemId::Rags, 3);
+        let taken = inv.take_from(0, 1).unwrap();
+        assert_eq!(taken.count, 1);
+        assert_eq!(inv.count(ItemId::Rags), 2);
+        inv.take_from(0, 5);
+        assert!(inv.stacks.is_empty());
+    }
+}
@@ -1,10 +1,11 @@
-use color_eyre::Result;
+use color_eyre::{eyre::eyre, Result};
 use crossterm::{
-    event::{self, Event, KeyCode},
+    event::{self, Event, KeyCode, KeyEvent},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use rand::{prelude::ThreadRng, Rng};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use ratatui::{
     backend::CrosstermBackend,
     layout::{Constraint, Direction, Layout},
@@ -13,33 +14,227 @@ use ratatui::{
     widgets::{Block, Borders, Paragraph, Table, Row, Cell},
     Terminal,
 };
+use serde::{Deserialize, Serialize};
 use std::io;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Instant;
+use barricade::{Barricade, BARRICADE_HP, BARRICADE_TURNS};
+use items::{GroundItem, Inventory, ItemCategory, ItemId, ItemStack, UseEffect};
+use clock::{Clock, Phase};
+use history::RunRecord;
+use mapgen::MapKind;
+use outcome::{CauseOfDeath, Outcome};
+use replay::{Playback, Recorder, Replay};
+use siege::Siege;
+use stats::RunStats;
+use noise::{NoiseEvent, NoiseKind};
+use pathfinding::FlowField;
+use weather::{Weather, WeatherState};
+use world::{World, STREET_LEVEL, UPPER_LEVEL};
+
+mod barricade;
+mod clock;
+mod crafting;
+mod fov;
+mod history;
+mod items;
+mod mapgen;
+mod noise;
+mod outcome;
+mod pathfinding;
+mod replay;
+mod save;
+mod siege;
+mod stats;
+mod targeting;
+mod weapons;
+mod weather;
+mod world;
 
 // --- STATE MANAGEMENT ENUM ---
-#[derive(PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 enum AppMode {
     Menu,
     Game,
+    Inventory,
+    Targeting,
+    Crafting,
+    Diagnostics,
+    QuitPrompt,
     GameOver,
+    Hall,
 }
 
 // --- Entity Constants and Structs ---
 const MAX_ZOMBIES: usize = 25;
-const ZOMBIE_SPAWN_CHANCE: f32 = 0.5;
 const ZOMBIE_SPAWN_RADIUS: i32 = 8;
+const FOV_RADIUS: i32 = 12;
+const ZOMBIE_PATH_MAX_COST: u32 = 24;
+/// A zombie may head for a noise from the edge of its reach, and walls make
+/// the walk longer than the straight line, so this search goes further.
+const ZOMBIE_INVESTIGATE_MAX_COST: u32 = 2 * noise::MAX_RADIUS as u32;
+const ZOMBIE_WANDER_CHANCE: f64 = 0.2;
+const ZOMBIE_WANDER_RADIUS: i32 = 6;
+/// In-game minutes that pass while resting in a building.
+const REST_MINUTES: u32 = 120;
+/// Turns it takes light rain to fill a bottle; a storm takes half as long.
+const RAIN_FILL_TURNS: u32 = 4;
+/// Alerted zombies within this many steps of the stairs follow the player.
+const ZOMBIE_FOLLOW_RANGE: i32 = 3;
+/// How far a molotov can be thrown, in tiles.
+const FIREBOMB_RANGE: i32 = 5;
+
+/// What a zombie currently knows about the player.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum Awareness {
+    /// Standing around with no idea where the player is.
+    Idle,
+    /// Shuffling toward a random nearby spot.
+    Wandering { x: i32, y: i32 },
+    /// The player is seen or heard this turn, last noticed at `(x, y)`.
+    Chasing { x: i32, y: i32 },
+    /// Contact was lost; heading for where the player was last noticed.
+    Investigating { x: i32, y: i32 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum ZombieKind {
+    Shambler,
+    Sprinter,
+    Crawler,
+    Brute,
+}
+
+/// Energy a zombie spends per action; each turn it gains `ZombieKind::speed`.
+const ZOMBIE_ACTION_COST: u8 = 2;
+
+impl ZombieKind {
+    const ALL: [ZombieKind; 4] = [ZombieKind::Shambler, ZombieKind::Sprinter, ZombieKind::Crawler, ZombieKind::Brute];
+
+    fn name(self) -> &'static str {
+        match self {
+            ZombieKind::Shambler => "shambler",
+            ZombieKind::Sprinter => "sprinter",
+            ZombieKind::Crawler => "crawler",
+            ZombieKind::Brute => "brute",
+        }
+    }
+
+    /// Energy gained per turn: sprinters act twice, crawlers every other turn.
+    fn speed(self) -> u8 {
+        match self {
+            ZombieKind::Shambler => 2,
+            ZombieKind::Sprinter => 4,
+            ZombieKind::Crawler => 1,
+            ZombieKind::Brute => 2,
+        }
+    }
+
+    fn max_hp(self) -> i32 {
+        match self {
+            ZombieKind::Shambler => 10,
+            ZombieKind::Sprinter => 6,
+            ZombieKind::Crawler => 6,
+            ZombieKind::Brute => 25,
+        }
+    }
+
+    /// Damage dealt to a barricade per blow.
+    fn bash_damage(self) -> i32 {
+        match self {
+            ZombieKind::Shambler => 1,
+            ZombieKind::Sprinter => 1,
+            ZombieKind::Crawler => 1,
+            ZombieKind::Brute => 4,
+        }
+    }
+
+    fn bite_damage(self) -> i32 {
+        match self {
+            ZombieKind::Shambler => 1,
+            ZombieKind::Sprinter => 1,
+            ZombieKind::Crawler => 2,
+            ZombieKind::Brute => 3,
+        }
+    }
+
+    fn sight_radius(self) -> i32 {
+        match self {
+            ZombieKind::Shambler => 8,
+            ZombieKind::Sprinter => 10,
+            ZombieKind::Crawler => 5,
+            ZombieKind::Brute => 6,
+        }
+    }
+
+    fn hearing_radius(self) -> i32 {
+        match self {
+            ZombieKind::Shambler => 3,
+            ZombieKind::Sprinter => 4,
+            ZombieKind::Crawler => 2,
+            ZombieKind::Brute => 3,
+        }
+    }
+
+    fn glyph(self) -> char {
+        match self {
+            ZombieKind::Shambler => 'Z',
+            ZombieKind::Sprinter => 'S',
+            ZombieKind::Crawler => 'X',
+            ZombieKind::Brute => 'H',
+        }
+    }
+
+    /// Spawn weight; sprinters and brutes grow more common as notes are found.
+    fn spawn_weight(self, notes_collected: u32) -> u32 {
+        let p = notes_collected.min(5);
+        match self {
+            ZombieKind::Shambler => 60 - 6 * p,
+            ZombieKind::Sprinter => 8 + 6 * p,
+            ZombieKind::Crawler => 20,
+            ZombieKind::Brute => 2 + 4 * p,
+        }
+    }
 
-#[derive(Clone, Copy, Debug)]
+    fn roll(rng: &mut (impl Rng + ?Sized), notes_collected: u32) -> Self {
+        let total: u32 = Self::ALL.iter().map(|k| k.spawn_weight(notes_collected)).sum();
+        let mut pick = rng.gen_range(0..total);
+        for kind in Self::ALL {
+            let weight = kind.spawn_weight(notes_collected);
+            if pick < weight {
+                return kind;
+            }
+            pick -= weight;
+        }
+        ZombieKind::Shambler
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 struct Zombie {
     x: i32,
     y: i32,
     hp: i32, 
+    kind: ZombieKind,
+    awareness: Awareness,
+    energy: u8,
+}
+
+impl Zombie {
+    fn new(x: i32, y: i32, kind: ZombieKind) -> Self {
+        Zombie { x, y, hp: kind.max_hp(), kind, awareness: Awareness::Idle, energy: 0 }
+    }
+
+    fn is_alerted(&self) -> bool {
+        matches!(self.awareness, Awareness::Chasing { .. })
+    }
 }
 
 // --- Enums and Structs ---
 
-#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum TileType {
     Wall,
     Floor,
@@ -51,6 +246,18 @@ pub enum TileType {
     Mall,
     Weapon, 
     Note, 
+    Door,
+    Window,
+    StairsUp,
+    StairsDown,
+    Helipad,
+}
+
+impl TileType {
+    /// Walls, wrecked cars and buildings stop line of sight.
+    pub fn blocks_sight(self) -> bool {
+        matches!(self, TileType::Wall | TileType::Car | TileType::Building)
+    }
 }
 
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
@@ -61,7 +268,7 @@ pub enum Shade {
     Bright,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Rect {
     pub x1: i32,
     pub y1: i32,
@@ -78,7 +285,7 @@ impl Rect {
         ((self.x1 + self.x2) / 2, (self.y1 + self.y2) / 2)
     }
 
-    pub fn create_room(&self, rng: &mut ThreadRng) -> Self {
+    pub fn create_room(&self, rng: &mut (impl Rng + ?Sized)) -> Self {
         let mut room = *self;
         room.x1 += rng.gen_range(1..=4);
         room.y1 += rng.gen_range(1..=4);
@@ -91,23 +298,36 @@ impl Rect {
         }
     }
 
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x1 && x <= self.x2 && y >= self.y1 && y <= self.y2
+    }
+
     pub fn intersects(&self, other: &Rect) -> bool {
         self.x1 <= other.x2 && self.x2 >= other.x1 && self.y1 <= other.y2 && self.y2 >= other.y1
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Map {
     pub tiles: Vec<TileType>,
     pub width: usize,
     pub height: usize,
     pub rooms: Vec<Rect>,
+    /// Areas with a roof overhead, out of the rain.
+    pub roofs: Vec<Rect>,
+    /// Tiles the player has seen at least once; drawn from memory when out of sight.
+    pub revealed: Vec<bool>,
+    /// Items lying on the floor, e.g. things the player dropped.
+    pub ground_items: Vec<GroundItem>,
+    /// Boarded-up doors and windows.
+    pub barricades: Vec<Barricade>,
 }
 
 impl Map {
     pub fn new(width: usize, height: usize) -> Self {
         let tiles = vec![TileType::Wall; width * height];
-        Map { tiles, width, height, rooms: vec![] }
+        let revealed = vec![false; width * height];
+        Map { tiles, width, height, rooms: vec![], roofs: vec![], revealed, ground_items: vec![], barricades: vec![] }
     }
 
     pub fn xy_idx(&self, x: i32, y: i32) -> usize {
@@ -119,10 +339,20 @@ impl Map {
     }
 
     pub fn is_walkable(&self, x: i32, y: i32) -> bool {
+        self.is_passable(x, y) && !self.is_barricaded(x, y)
+    }
+
+    /// Like `is_walkable`, but boarded doors and windows count as open:
+    /// zombies route into them and hammer their way through.
+    pub fn is_passable(&self, x: i32, y: i32) -> bool {
         self.in_bounds(x, y) && self.tiles[self.xy_idx(x, y)] != TileType::Wall
     }
 
-    pub fn apply_room(&mut self, room: &Rect, _rng: &mut ThreadRng) {
+    pub fn blocks_sight(&self, x: i32, y: i32) -> bool {
+        self.tiles[self.xy_idx(x, y)].blocks_sight() || self.is_barricaded(x, y)
+    }
+
+    pub fn apply_room(&mut self, room: &Rect) {
         for y in room.y1..=room.y2 {
             for x in room.x1..=room.x2 {
                 if self.in_bounds(x, y) {
@@ -155,169 +385,93 @@ impl Map {
         }
     }
 
-    pub fn generate_bsp(&mut self, rng: &mut ThreadRng) {
-        println!("BSP gen firing—target 10-20 rooms");
-        let mut rects = vec![Rect::new(1, 1, self.width as i32 - 2, self.height as i32 - 2)];
-
-        while !rects.is_empty() && self.rooms.len() < 20 {
-            let idx = rng.gen_range(0..rects.len());
-            let current = rects.swap_remove(idx);
-
-            let room = current.create_room(rng);
-            let valid = !self.rooms.iter().any(|r| room.intersects(r));
-            if valid {
-                self.apply_room(&room, rng); 
-                self.rooms.push(room);       
-            }
-
-            if current.x2 - current.x1 >= 12 && current.y2 - current.y1 >= 12 {
-                let h_split = rng.gen_bool(0.5);
-                let split_dim_size = if h_split { current.y2 - current.y1 } else { current.x2 - current.x1 };
-                if split_dim_size > 8 {
-                    let split = if h_split {
-                        rng.gen_range(current.y1 + 4..current.y2 - 4)
-                    } else {
-                        rng.gen_range(current.x1 + 4..current.x2 - 4)
-                    };
-
-                    let child1 = if h_split {
-                        Rect::new(current.x1, current.y1, current.x2, split)
-                    } else {
-                        Rect::new(current.x1, current.y1, split, current.y2)
-                    };
-
-                    let child2 = if h_split {
-                        Rect::new(current.x1, split, current.x2, current.y2)
-                    } else {
-                        Rect::new(split, current.y1, current.x2, current.y2)
-                    };
+    /// Tiles visible from `(px, py)`, shaded by distance. Anything missing from
+    /// the result is out of sight; if it is `revealed` it is drawn as `Shade::Dark`.
+    pub fn compute_fov(&self, px: i32, py: i32, radius: i32) -> HashMap<(i32, i32), Shade> {
+        fov::compute(self, px, py, radius)
+    }
 
-                    if child1.x2 - child1.x1 >= 6 && child1.y2 - child1.y1 >= 6 {
-                        rects.push(child1);
-                    }
-                    if child2.x2 - child2.x1 >= 6 && child2.y2 - child2.y1 >= 6 {
-                        rects.push(child2);
-                    }
-                }
-            }
+    pub fn reveal(&mut self, fov: &HashMap<(i32, i32), Shade>) {
+        for &(x, y) in fov.keys() {
+            let idx = self.xy_idx(x, y);
+            self.revealed[idx] = true;
         }
+    }
 
-        println!("Gen done: {} rooms carved", self.rooms.len());
+    pub fn is_revealed(&self, x: i32, y: i32) -> bool {
+        self.in_bounds(x, y) && self.revealed[self.xy_idx(x, y)]
+    }
 
-        for i in 1..self.rooms.len() {
-            let (prev_x, prev_y) = self.rooms[i - 1].center();
-            let (curr_x, curr_y) = self.rooms[i].center();
-            
-            if rng.gen_bool(0.5) {
-                self.apply_h_tunnel(prev_x, curr_x, prev_y);
-                self.apply_v_tunnel(curr_y, prev_y, curr_x);
-            } else {
-                self.apply_v_tunnel(prev_y, curr_y, prev_x);
-                self.apply_h_tunnel(curr_x, prev_x, curr_y);
-            }
+    pub fn has_ground_item(&self, x: i32, y: i32) -> bool {
+        self.ground_items.iter().any(|g| g.x == x && g.y == y)
+    }
 
-            // Tunnel Zombie
-            if i == 1 {
-                let (z_x, z_y) = if prev_x != curr_x {
-                    ((prev_x + curr_x) / 2, prev_y)
-                } else {
-                    (prev_x, (prev_y + curr_y) / 2)
-                };
-                if self.in_bounds(z_x, z_y) {
-                    let idx = self.xy_idx(z_x, z_y);
-                    if self.tiles[idx] == TileType::Floor {
-                         self.tiles[idx] = TileType::Zombie; 
-                    }
-                }
-            }
-        }
-        
-        // --- Static Population & Feature Generation ---
-        let first_room_rect = self.rooms.first().copied();
+    pub fn is_barricaded(&self, x: i32, y: i32) -> bool {
+        self.barricades.iter().any(|b| b.x == x && b.y == y)
+    }
 
-        for y in 0..self.height as i32 {
-            for x in 0..self.width as i32 {
-                let idx = self.xy_idx(x, y);
-                
-                if self.tiles[idx] != TileType::Floor {
-                    continue;
-                }
-                
-                // 1. Place FEATURES (Foliage, Car, etc.)
-                if rng.gen_bool(0.08) { 
-                    match rng.gen_range(0..25) { 
-                        0..=10 => self.tiles[idx] = TileType::Foliage,
-                        11..=17 => self.tiles[idx] = TileType::Car,
-                        18..=20 => self.tiles[idx] = TileType::Resource,
-                        21..=23 => self.tiles[idx] = TileType::Building,
-                        24 => self.tiles[idx] = TileType::Weapon, 
-                        _ => {}
-                    }
-                }
-            }
-        }
+    pub fn barricade_at(&self, x: i32, y: i32) -> Option<&Barricade> {
+        self.barricades.iter().find(|b| b.x == x && b.y == y)
+    }
 
-        // --- Note Placement Logic (5 Notes) ---
-        let mut notes_placed = 0;
-        while notes_placed < 5 {
-            let x = rng.gen_range(1..self.width as i32 - 1);
-            let y = rng.gen_range(1..self.height as i32 - 1);
-            let idx = self.xy_idx(x, y);
+    /// Boards up `(x, y)`, or restores an existing barricade to full HP.
+    pub fn board(&mut self, x: i32, y: i32) {
+        self.barricades.retain(|b| (b.x, b.y) != (x, y));
+        self.barricades.push(Barricade::new(x, y));
+    }
 
-            if self.tiles[idx] == TileType::Floor {
-                self.tiles[idx] = TileType::Note;
-                notes_placed += 1;
-            }
+    /// Hits the barricade at `(x, y)`. Returns `Some(true)` if it broke (and
+    /// is gone), `Some(false)` if it held, `None` if there was none.
+    pub fn hammer_barricade(&mut self, x: i32, y: i32, damage: i32) -> Option<bool> {
+        let idx = self.barricades.iter().position(|b| b.x == x && b.y == y)?;
+        let broken = self.barricades[idx].hammer(damage);
+        if broken {
+            self.barricades.remove(idx);
         }
+        Some(broken)
+    }
 
-        // 2. Clear the last room of Mall/other tiles (will be set later)
-        if self.rooms.len() > 1 {
-            if let Some(last_room) = self.rooms.last() {
-                for y in last_room.y1..=last_room.y2 {
-                    for x in last_room.x1..=last_room.x2 {
-                        if self.in_bounds(x, y) {
-                            let idx = self.xy_idx(x, y);
-                            if self.tiles[idx] != TileType::Wall {
-                                self.tiles[idx] = TileType::Floor;
-                            }
-                        }
-                    }
-                }
-            }
-        }
+    pub fn drop_item(&mut self, x: i32, y: i32, stack: ItemStack) {
+        self.ground_items.push(GroundItem { x, y, stack });
     }
 
-    pub fn compute_fov(&self, px: i32, py: i32, radius: i32) -> HashMap<(i32, i32), Shade> {
-        let mut visible: HashMap<(i32, i32), Shade> = HashMap::new();
-        for dy in -radius..=radius {
-            for dx in -radius..=radius {
-                if dx * dx + dy * dy > radius * radius { continue; }
-                let tx = px + dx;
-                let ty = py + dy;
-
-                if !self.in_bounds(tx, ty) { continue; }
-
-                let dist = ((dx * dx + dy * dy) as f32).sqrt();
-                let blocked = self.tiles[self.xy_idx(tx, ty)] == TileType::Wall;
-
-                let shade = if blocked {
-                    Shade::Dark
-                } else if dist > (radius as f32) * 0.7f32 {
-                    Shade::Dim
-                } else if dist > (radius as f32) * 0.3f32 {
-                    Shade::Lit
-                } else {
-                    Shade::Bright
+    /// Removes and returns everything lying at `(x, y)`.
+    pub fn take_ground_items(&mut self, x: i32, y: i32) -> Vec<ItemStack> {
+        let (here, rest): (Vec<GroundItem>, Vec<GroundItem>) =
+            self.ground_items.drain(..).partition(|g| g.x == x && g.y == y);
+        self.ground_items = rest;
+        here.into_iter().map(|g| g.stack).collect()
+    }
+}
+
+#[cfg(test)]
+impl Map {
+    /// Test fixture builder: `#` wall, `C` car, `B` building, `+` door, `=`
+    /// window, `<` stairs up, `>` stairs down, anything else floor.
+    pub fn from_ascii(rows: &[&str]) -> Self {
+        let mut map = Map::new(rows[0].len(), rows.len());
+        for (y, row) in rows.iter().enumerate() {
+            for (x, ch) in row.chars().enumerate() {
+                let idx = map.xy_idx(x as i32, y as i32);
+                map.tiles[idx] = match ch {
+                    '#' => TileType::Wall,
+                    'C' => TileType::Car,
+                    'B' => TileType::Building,
+                    '+' => TileType::Door,
+                    '=' => TileType::Window,
+                    '<' => TileType::StairsUp,
+                    '>' => TileType::StairsDown,
+                    _ => TileType::Floor,
                 };
-                visible.insert((tx, ty), shade);
             }
         }
-        visible
+        map
     }
 }
 
 // --- Game State (Restored and Zombie List Added) ---
 
+#[derive(Serialize, Deserialize)]
 struct GameState {
     player_x: i32,
     player_y: i32,
@@ -325,26 +479,41 @@ struct GameState {
     thirst: i32, 
     hunger: i32,
     fatigue: i32,
-    ammo: i32,   
-    inventory: Vec<String>,
+    inventory: Inventory,
+    /// Weapon in hand; `None` means fighting with bare fists.
+    equipped: Option<ItemId>,
     message_log: Vec<String>,
     zombies: Vec<Zombie>, 
     move_counter: u32, 
     notes_collected: u32, 
     journal: Vec<String>, 
+    seed: u64,
+    /// Noises made during the current turn; cleared when the next action starts.
+    noises: Vec<NoiseEvent>,
+    clock: Clock,
+    weather: WeatherState,
+    /// Index into `World::levels` of the level the player is on.
+    level: usize,
+    /// Which generator built the world; with `seed` it reproduces the run.
+    map_kind: MapKind,
+    /// Set once the run is decided; the main loop then moves to `GameOver`.
+    outcome: Option<Outcome>,
+    /// The rooftop hold-out, once the flare is fired.
+    siege: Option<Siege>,
+    stats: RunStats,
 }
 
 impl GameState {
-    fn new() -> Self {
-        Self {
+    fn new(seed: u64) -> Self {
+        let mut state = Self {
             player_x: 0,
             player_y: 0,
             health: 100,
             thirst: 100,
             hunger: 100,
             fatigue: 300,
-            ammo: 10,
-            inventory: vec!["rusty knife".to_string()],
+            inventory: Inventory::default(),
+            equipped: Some(ItemId::RustyKnife),
             message_log: vec![
                 "Radio crackles: 'Help, the horde's at the mall!'".to_string(),
                 "Tip: Press 'T' to throw an item and distract an adjacent zombie.".to_string(), 
@@ -353,49 +522,74 @@ impl GameState {
             move_counter: 0, 
             notes_collected: 0, 
             journal: vec![], 
+            seed,
+            noises: vec![],
+            clock: Clock::default(),
+            weather: WeatherState::default(),
+            level: STREET_LEVEL,
+            map_kind: MapKind::Bsp,
+            outcome: None,
+            siege: None,
+            stats: RunStats::default(),
+        };
+        state.inventory.add(ItemId::RustyKnife, 1);
+        state.inventory.add(ItemId::PistolAmmo, 10);
+        state.inventory.add(ItemId::EmptyBottle, 1);
+        state
+    }
+
+    /// Takes health; if this is the blow that kills, `cause` goes on the record.
+    fn hurt(&mut self, amount: i32, cause: CauseOfDeath) {
+        self.health = self.health.saturating_sub(amount);
+        if self.health <= 0 && self.outcome.is_none() {
+            self.outcome = Some(Outcome::Death(cause));
+        }
+    }
+
+    fn make_noise(&mut self, x: i32, y: i32, kind: NoiseKind) {
+        self.noises.push(NoiseEvent::new(x, y, kind).through(self.weather.current));
+    }
+
+    fn apply_effect(&mut self, effect: UseEffect) {
+        match effect {
+            UseEffect::Hunger(n) => self.hunger = self.hunger.saturating_add(n).min(100),
+            UseEffect::Thirst(n) => self.thirst = self.thirst.saturating_add(n).min(100),
+            UseEffect::Heal(n) => self.health = self.health.saturating_add(n).min(100),
+            UseEffect::None | UseEffect::Firebomb(_) => {}
         }
     }
+
+    /// How far the player can see right now.
+    fn view_radius(&self) -> i32 {
+        self.weather.current.view_radius(self.clock.phase().view_radius(FOV_RADIUS))
+    }
 }
 
 // --- Core Game Functions ---
 
-fn init_game(rng: &mut ThreadRng) -> (Map, GameState) {
-    let map_width = 160; 
-    let map_height = 47; 
-    let mut game_map = Map::new(map_width, map_height);
-    game_map.generate_bsp(rng);
+/// Builds a fresh run from a seed; the same seed and map kind always give
+/// the same world.
+fn start_run(seed: u64, map_kind: MapKind) -> Result<(World, GameState, ChaCha8Rng)> {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let (world, state) = init_game(seed, map_kind, &mut rng)?;
+    Ok((world, state, rng))
+}
 
-    let mut state = GameState::new();
-    
-    // Player spawn logic
-    if let Some(first_room) = game_map.rooms.first() {
-        let (px, py) = first_room.center();
-        let mut spawn_x = px;
-        let mut spawn_y = py;
-        
-        for dy in -1..=1 {
-            for dx in -1..=1 {
-                if game_map.in_bounds(px + dx, py + dy) && game_map.tiles[game_map.xy_idx(px + dx, py + dy)] != TileType::Wall {
-                    spawn_x = px + dx;
-                    spawn_y = py + dy;
-                    break;
-                }
-            }
-        }
-        
-        if game_map.in_bounds(spawn_x, spawn_y) {
-            state.player_x = spawn_x;
-            state.player_y = spawn_y;
-            let idx = game_map.xy_idx(spawn_x, spawn_y);
-            game_map.tiles[idx] = TileType::Floor; 
-        } else {
-            state.player_x = 1; 
-            state.player_y = 1;
-        }
-    } else {
-        state.player_x = 1; 
-        state.player_y = 1;
-    }
+/// Files a finished run in the history; returns a notice for the menu if
+/// that failed.
+fn record_run(state: &GameState) -> Option<String> {
+    history::record(RunRecord::new(state, history::now()))
+        .err()
+        .map(|err| format!("Run not recorded: {}", err))
+}
+
+fn init_game(seed: u64, map_kind: MapKind, rng: &mut ChaCha8Rng) -> Result<(World, GameState)> {
+    let generated = mapgen::generate_valid(map_kind.generator(), 160, 47, rng)?;
+    let game_map = generated.map;
+
+    let mut state = GameState::new(seed);
+    state.map_kind = map_kind;
+    (state.player_x, state.player_y) = generated.spawn;
 
     // Spawn initial tunnel zombie
     if game_map.rooms.len() > 1 {
@@ -411,15 +605,73 @@ fn init_game(rng: &mut ThreadRng) -> (Map, GameState) {
         if game_map.in_bounds(z_x, z_y) {
             let idx = game_map.xy_idx(z_x, z_y);
             if game_map.tiles[idx] == TileType::Zombie {
-                state.zombies.push(Zombie { x: z_x, y: z_y, hp: 10 }); 
+                state.zombies.push(Zombie::new(z_x, z_y, ZombieKind::Shambler)); 
             }
         }
     }
 
-    (game_map, state)
+    let mut world = World::new(vec![game_map, generated.upper]);
+    world.park(UPPER_LEVEL, generated.upper_zombies);
+    world.notes = generated.placements;
+
+    Ok((world, state))
+}
+
+/// Takes the stairs under the player. Alerted zombies close behind follow
+/// and arrive around the top (or bottom) of the stairs. Returns the turns
+/// spent.
+fn use_stairs(world: &mut World, state: &mut GameState, up: bool) -> u32 {
+    let (px, py) = (state.player_x, state.player_y);
+    let from = state.level;
+    let tile = world.levels[from].tiles[world.levels[from].xy_idx(px, py)];
+    let to = match (tile, up) {
+        (TileType::StairsUp, true) => from + 1,
+        (TileType::StairsDown, false) => from - 1,
+        _ => {
+            let key = if up { '<' } else { '>' };
+            state.message_log.push(format!("There are no stairs here to use with '{}'.", key));
+            return 0;
+        }
+    };
+
+    let (followers, left_behind): (Vec<Zombie>, Vec<Zombie>) = std::mem::take(&mut state.zombies)
+        .into_iter()
+        .partition(|z| z.is_alerted() && (z.x - px).abs() + (z.y - py).abs() <= ZOMBIE_FOLLOW_RANGE);
+    world.park(from, left_behind);
+    state.zombies = world.unpark(to);
+    state.level = to;
+
+    let dest = &world.levels[to];
+    let mut free: Vec<(i32, i32)> = (-2..=2)
+        .flat_map(|dy| (-2..=2).map(move |dx| (px + dx, py + dy)))
+        .filter(|&(x, y)| (x, y) != (px, py) && dest.is_walkable(x, y))
+        .filter(|&(x, y)| !state.zombies.iter().any(|z| (z.x, z.y) == (x, y)))
+        .collect();
+    free.sort_by_key(|&(x, y)| (x - px).abs() + (y - py).abs());
+    let mut free = free.into_iter();
+    let mut following = 0;
+    for mut zombie in followers {
+        // No room at the top of the stairs: it waits where it was.
+        let Some((x, y)) = free.next() else {
+            world.park(from, vec![zombie]);
+            continue;
+        };
+        zombie.x = x;
+        zombie.y = y;
+        state.zombies.push(zombie);
+        following += 1;
+    }
+
+    let verb = if up { "climb up" } else { "head down" };
+    state.message_log.push(format!("You {} the stairs. ({})", verb, World::level_name(to)));
+    if following > 0 {
+        state.message_log.push(format!("{} zombie(s) stumble after you!", following));
+    }
+    while state.message_log.len() > 10 { state.message_log.remove(0); }
+    1
 }
 
-fn handle_attack(map: &mut Map, state: &mut GameState, tx: i32, ty: i32, rng: &mut ThreadRng) {
+fn handle_attack(_map: &mut Map, state: &mut GameState, tx: i32, ty: i32, rng: &mut impl Rng) {
     let zombie_index = state.zombies.iter().position(|z| z.x == tx && z.y == ty);
 
     if zombie_index.is_none() {
@@ -432,61 +684,112 @@ fn handle_attack(map: &mut Map, state: &mut GameState, tx: i32, ty: i32, rng: &m
     state.thirst = state.thirst.saturating_sub(1);
 
     // --- Weapon and Damage Calculation ---
-    let weapon_used: &str;
-    let base_hit_chance: f32;
-    let damage_range: std::ops::RangeInclusive<i32>;
-    let ammo_cost: i32;
-
-    if state.inventory.contains(&"gun".to_string()) && state.ammo > 0 {
-        base_hit_chance = 0.95;
-        damage_range = 10..=15;
-        weapon_used = "Gun";
-        ammo_cost = 1;
-    } else {
-        base_hit_chance = 0.50;
-        damage_range = 1..=3;
-        weapon_used = "Rusty Knife";
-        ammo_cost = 0;
+    let weapon = weapons::held(state.equipped);
+    let dist = targeting::distance(state.player_x, state.player_y, tx, ty);
+    if dist > weapon.reach as f32 {
+        state.message_log.push(format!("Out of range for the {}.", weapon.name));
+        return;
+    }
+
+    if let Some(ammo) = weapon.ammo {
+        if !state.inventory.remove(ammo, 1) {
+            state.message_log.push(format!("*click* The {} is empty! Press H to holster it.", weapon.name));
+            while state.message_log.len() > 10 { state.message_log.remove(0); }
+            return;
+        }
     }
+    state.make_noise(state.player_x, state.player_y, weapon.noise);
 
     // --- Fatigue Modifier ---
     let fatigue_penalty = (100 - state.fatigue).max(0) as f32 / 100.0 * 0.2;
+    let base_hit_chance = targeting::hit_chance(weapon.hit_chance, dist, weapon.reach);
     let total_hit_chance = (base_hit_chance - fatigue_penalty).max(0.0);
     
     let zombie_hp_before = state.zombies[zombie_index.unwrap()].hp;
 
-    if rng.gen_bool(total_hit_chance as f64) {
-        let damage = rng.gen_range(damage_range);
+    let hit = rng.gen_bool(total_hit_chance as f64);
+    if weapon.is_firearm() {
+        state.stats.record_shot(hit);
+    }
+    if hit {
+        let damage = rng.gen_range(weapon.damage.0..=weapon.damage.1);
         let zombie = &mut state.zombies[zombie_index.unwrap()];
         zombie.hp = zombie.hp.saturating_sub(damage);
         
         state.message_log.push(format!("{} HIT! Damage: {}. Zombie HP: {} -> {}. (Chance: {:.0}%)", 
-            weapon_used, damage, zombie_hp_before, zombie.hp, total_hit_chance * 100.0));
-        
-        if ammo_cost > 0 {
-            state.ammo = state.ammo.saturating_sub(ammo_cost);
-        }
+            weapon.name, damage, zombie_hp_before, zombie.hp, total_hit_chance * 100.0));
 
         // --- Victory Check ---
         if zombie.hp <= 0 {
-            state.message_log.push(format!("Zombie dispatched by final {} blow!", weapon_used));
+            state.message_log.push(format!("Zombie dispatched by final {} blow!", weapon.name));
             state.zombies.remove(zombie_index.unwrap());
+            state.stats.record_kill(weapon.name, 1);
         }
 
     } else {
         state.message_log.push(format!("{} MISSED! (Chance: {:.0}%)", 
-            weapon_used, total_hit_chance * 100.0));
+            weapon.name, total_hit_chance * 100.0));
         
-        // --- Zombie Counter-Attack ---
-        state.health = state.health.saturating_sub(1); 
-        state.message_log.push("Zombie counter-attacks! (-1 Health)".to_string()); 
+        // --- Zombie Counter-Attack (only when it is close enough to bite) ---
+        if dist <= 1.0 {
+            let kind = state.zombies[zombie_index.unwrap()].kind;
+            let bite = kind.bite_damage();
+            state.hurt(bite, CauseOfDeath::Bite(kind));
+            state.message_log.push(format!("Zombie counter-attacks! (-{} Health)", bite)); 
+        }
     }
+
+    wear_equipped_weapon(state);
     
     while state.message_log.len() > 10 { state.message_log.remove(0); }
 }
 
+/// Every swing or shot costs the held weapon one point of durability; at zero
+/// it breaks and is gone from the backpack.
+fn wear_equipped_weapon(state: &mut GameState) {
+    let Some(id) = state.equipped else { return };
+    let Some(index) = state.inventory.stacks.iter().position(|s| s.id == id) else { return };
+
+    let stack = &mut state.inventory.stacks[index];
+    if let Some(left) = stack.durability.as_mut() {
+        *left = left.saturating_sub(1);
+        if *left == 0 {
+            state.inventory.take_from(index, 1);
+            state.message_log.push(format!("Your {} breaks!", id.name()));
+            if !state.inventory.has(id) {
+                state.equipped = None;
+            }
+        }
+    }
+}
+
+/// Swaps between a firearm and the best melee weapon in the bag, so ammo can
+/// be saved for when it matters.
+fn toggle_holster(state: &mut GameState) {
+    let held = weapons::held(state.equipped);
+    let best_by = |firearm: bool| {
+        state.inventory.stacks.iter()
+            .filter_map(|s| weapons::weapon_def(s.id).map(|w| (s.id, w)))
+            .filter(|(_, w)| w.is_firearm() == firearm)
+            .max_by_key(|(_, w)| w.damage.1)
+            .map(|(id, _)| id)
+    };
+
+    if held.is_firearm() {
+        state.equipped = best_by(false);
+        let name = state.equipped.map(|id| id.name()).unwrap_or("fists");
+        state.message_log.push(format!("You holster the {} and ready your {}.", held.name.to_lowercase(), name));
+    } else if let Some(gun) = best_by(true) {
+        state.equipped = Some(gun);
+        state.message_log.push(format!("You draw the {}.", gun.name()));
+    } else {
+        state.message_log.push("You have no firearm to draw.".to_string());
+    }
+    while state.message_log.len() > 10 { state.message_log.remove(0); }
+}
+
 fn spawn_random_zombies(
-    rng: &mut ThreadRng,
+    rng: &mut impl Rng,
     game_map: &Map,
     state: &mut GameState,
 ) {
@@ -494,25 +797,26 @@ fn spawn_random_zombies(
         return;
     }
 
-    if rng.gen_range(0..100) < 10 { 
+    if rng.gen_range(0..100) < state.clock.phase().spawn_chance() { 
         let mut attempts = 0;
         while attempts < 10 {
             let x = state.player_x + rng.gen_range(-ZOMBIE_SPAWN_RADIUS..=ZOMBIE_SPAWN_RADIUS);
             let y = state.player_y + rng.gen_range(-ZOMBIE_SPAWN_RADIUS..=ZOMBIE_SPAWN_RADIUS);
 
-            if game_map.in_bounds(x, y) && game_map.tiles[game_map.xy_idx(x, y)] == TileType::Floor {
-                if !state.zombies.iter().any(|z| z.x == x && z.y == y) {
-                    state.zombies.push(Zombie { x, y, hp: 10 }); 
-                    state.message_log.push("You hear distant groaning...".to_string());
-                    break;
-                }
+            if game_map.in_bounds(x, y) && game_map.tiles[game_map.xy_idx(x, y)] == TileType::Floor
+                && !state.zombies.iter().any(|z| z.x == x && z.y == y)
+            {
+                let kind = ZombieKind::roll(rng, state.notes_collected);
+                state.zombies.push(Zombie::new(x, y, kind)); 
+                state.message_log.push("You hear distant groaning...".to_string());
+                break;
             }
             attempts += 1;
         }
     }
 }
 
-fn handle_retreat_action(map: &Map, state: &mut GameState, rng: &mut ThreadRng) {
+fn handle_retreat_action(map: &Map, state: &mut GameState, _rng: &mut impl Rng) {
     state.fatigue = state.fatigue.saturating_sub(5); 
     state.hunger = state.hunger.saturating_sub(1);
     state.thirst = state.thirst.saturating_sub(1);
@@ -520,19 +824,18 @@ fn handle_retreat_action(map: &Map, state: &mut GameState, rng: &mut ThreadRng)
     if let Some(closest_zombie_index) = state.zombies.iter().position(|z| {
         (state.player_x - z.x).abs() + (state.player_y - z.y).abs() == 1
     }) {
-        if state.inventory.len() > 1 {
-            let non_weapon_index = state.inventory.iter().rposition(|item| item != "rusty knife" && item != "gun");
+        let throwable = state.inventory.stacks.iter().rev()
+            .map(|s| s.id)
+            .find(|id| !matches!(id.def().category, ItemCategory::Weapon | ItemCategory::Ammo));
 
-            let thrown_item = if let Some(idx) = non_weapon_index {
-                state.inventory.remove(idx)
-            } else {
-                state.inventory.pop().unwrap_or("nothing".to_string())
-            };
-            
-            state.message_log.push(format!("You threw your {} to distract the horde!", thrown_item));
+        if let Some(thrown_item) = throwable {
+            state.inventory.remove(thrown_item, 1);
+            state.message_log.push(format!("You threw your {} to distract the horde!", thrown_item.name()));
 
             let zombie = state.zombies.remove(closest_zombie_index);
-            state.message_log.push(format!("Retreat successful! Zombie dispatched by distraction."));
+            state.stats.record_kill("Distraction", 1);
+            state.message_log.push("Retreat successful! Zombie dispatched by distraction.".to_string());
+            state.make_noise(zombie.x, zombie.y, NoiseKind::Thrown);
 
             let dx = (state.player_x - zombie.x).signum();
             let dy = (state.player_y - zombie.y).signum();
@@ -544,6 +847,7 @@ fn handle_retreat_action(map: &Map, state: &mut GameState, rng: &mut ThreadRng)
                 if map.is_walkable(*nx, *ny) && !state.zombies.iter().any(|z| z.x == *nx && z.y == *ny) {
                     state.player_x = *nx;
                     state.player_y = *ny;
+                    state.stats.record_step();
                     escaped = true;
                     state.message_log.push("You scrambled back to safety.".to_string());
                     break;
@@ -555,7 +859,7 @@ fn handle_retreat_action(map: &Map, state: &mut GameState, rng: &mut ThreadRng)
             }
             
         } else {
-            state.message_log.push("You only have your main weapon! Cannot afford to retreat.".to_string());
+            state.message_log.push("Nothing to throw but your weapons! Cannot afford to retreat.".to_string());
         }
     } else {
         state.message_log.push("No adjacent zombie to retreat from.".to_string());
@@ -563,126 +867,479 @@ fn handle_retreat_action(map: &Map, state: &mut GameState, rng: &mut ThreadRng)
     while state.message_log.len() > 10 { state.message_log.remove(0); }
 }
 
+/// Picks a random walkable tile near `(x, y)` for an idle zombie to drift to.
+fn pick_wander_target(map: &Map, x: i32, y: i32, rng: &mut impl Rng) -> Option<(i32, i32)> {
+    for _ in 0..5 {
+        let tx = x + rng.gen_range(-ZOMBIE_WANDER_RADIUS..=ZOMBIE_WANDER_RADIUS);
+        let ty = y + rng.gen_range(-ZOMBIE_WANDER_RADIUS..=ZOMBIE_WANDER_RADIUS);
+        if (tx, ty) != (x, y) && map.is_walkable(tx, ty) {
+            return Some((tx, ty));
+        }
+    }
+    None
+}
+
 fn update_zombies(
-    map: &Map,
+    map: &mut Map,
     state: &mut GameState,
+    rng: &mut impl Rng,
 ) {
-    let occupied_positions: HashSet<(i32, i32)> = state.zombies.iter().map(|z| (z.x, z.y)).collect();
-
-    for zombie in state.zombies.iter_mut() {
-        let zx = zombie.x;
-        let zy = zombie.y;
-
-        let dx = (state.player_x - zx).signum();
-        let dy = (state.player_y - zy).signum();
-
-        let dist = (state.player_x - zx).abs() + (state.player_y - zy).abs();
+    let player = (state.player_x, state.player_y);
+    let mut occupied_positions: HashSet<(i32, i32)> = state.zombies.iter().map(|z| (z.x, z.y)).collect();
+    let field = FlowField::from_target(map, state.player_x, state.player_y, ZOMBIE_PATH_MAX_COST);
+    // Zombies heading for the same noise or last sighting share one field.
+    let mut fields: HashMap<(i32, i32), FlowField> = HashMap::new();
+
+    // Shadowcasting is symmetric, so the tiles the player could see at zombie
+    // sight range are exactly the tiles from which a zombie can see the player.
+    let max_sight = ZombieKind::ALL.iter().map(|k| k.sight_radius()).max().unwrap_or(0);
+    let seen_from = map.compute_fov(state.player_x, state.player_y, max_sight);
+    let phase = state.clock.phase();
+
+    // Taken out for the loop so bites can go through `state.hurt`.
+    let mut zombies = std::mem::take(&mut state.zombies);
+    for zombie in zombies.iter_mut() {
+        zombie.energy += zombie.kind.speed() + phase.speed_bonus();
+
+        while zombie.energy >= ZOMBIE_ACTION_COST {
+            zombie.energy -= ZOMBIE_ACTION_COST;
+
+            let zx = zombie.x;
+            let zy = zombie.y;
+            let (dx, dy) = (state.player_x - zx, state.player_y - zy);
+            let dist = dx.abs() + dy.abs();
+
+            // --- Perception ---
+            let sight = zombie.kind.sight_radius();
+            let sees = seen_from.contains_key(&(zx, zy)) && dx * dx + dy * dy <= sight * sight;
+            let hears = dist <= zombie.kind.hearing_radius() + phase.hearing_bonus();
+            if sees || hears {
+                if !zombie.is_alerted() && sees {
+                    state.message_log.push(format!("A {} turns its head toward you...", zombie.kind.name()));
+                }
+                zombie.awareness = Awareness::Chasing { x: player.0, y: player.1 };
+            } else if let Awareness::Chasing { x, y } = zombie.awareness {
+                zombie.awareness = Awareness::Investigating { x, y };
+            } else if let Some(noise) = noise::loudest_heard(&state.noises, zx, zy) {
+                zombie.awareness = Awareness::Investigating { x: noise.x, y: noise.y };
+            }
 
-        if dist == 1 {
-            state.health = state.health.saturating_sub(1); 
-            state.message_log.push("A zombie bites you! (-1 HP)".to_string()); 
-            continue; 
-        } else if dist > 1 && dist <= 12 { 
-            
-            let try_positions = [(zx + dx, zy), (zx, zy + dy)];
+            if dist == 1 {
+                let bite = zombie.kind.bite_damage();
+                state.hurt(bite, CauseOfDeath::Bite(zombie.kind));
+                state.message_log.push(format!("A {} bites you! (-{} HP)", zombie.kind.name(), bite)); 
+                continue; 
+            }
 
-            let mut moved = false;
-            for &(nx, ny) in &try_positions {
-                if !map.in_bounds(nx, ny) {
-                    continue;
+            // --- Movement ---
+            let blocked = |x: i32, y: i32| (x, y) == player || occupied_positions.contains(&(x, y));
+            let next = match zombie.awareness {
+                Awareness::Chasing { .. } => field.next_step(zx, zy, blocked),
+                Awareness::Investigating { x, y } | Awareness::Wandering { x, y } => {
+                    let step = if (zx, zy) == (x, y) {
+                        None
+                    } else {
+                        fields
+                            .entry((x, y))
+                            .or_insert_with(|| FlowField::from_target(map, x, y, ZOMBIE_INVESTIGATE_MAX_COST))
+                            .next_step(zx, zy, blocked)
+                    };
+                    if step.is_none() {
+                        zombie.awareness = Awareness::Idle;
+                    }
+                    step
+                }
+                Awareness::Idle => {
+                    if rng.gen_bool(ZOMBIE_WANDER_CHANCE) {
+                        if let Some((x, y)) = pick_wander_target(map, zx, zy, rng) {
+                            zombie.awareness = Awareness::Wandering { x, y };
+                        }
+                    }
+                    None
                 }
+            };
 
-                let target_tile = map.tiles[map.xy_idx(nx, ny)];
-                let is_walkable_tile = target_tile != TileType::Wall;
-                
-                let is_occupied_by_another_zombie = occupied_positions.contains(&(nx, ny)) && (nx != zx || ny != zy); 
-                
-                if is_walkable_tile && !is_occupied_by_another_zombie {
-                    zombie.x = nx;
-                    zombie.y = ny;
-                    moved = true;
-                    break;
+            if let Some((nx, ny)) = next {
+                if let Some(broken) = map.hammer_barricade(nx, ny, zombie.kind.bash_damage()) {
+                    if broken {
+                        let opening = if map.tiles[map.xy_idx(nx, ny)] == TileType::Window { "window" } else { "door" };
+                        state.make_noise(nx, ny, NoiseKind::BarricadeBreak);
+                        state.message_log.push(format!("CRASH! A {} smashes through the boarded {}!", zombie.kind.name(), opening));
+                    }
+                    continue;
                 }
+                occupied_positions.remove(&(zx, zy));
+                occupied_positions.insert((nx, ny));
+                zombie.x = nx;
+                zombie.y = ny;
             }
         }
     }
+    state.zombies = zombies;
     
     while state.message_log.len() > 10 { state.message_log.remove(0); }
 }
 
-fn get_note_content(note_id: u32) -> &'static str {
-    match note_id {
-        1 => "Note 1: 'They followed the sirens. The radio tower signal is strongest near the old shopping center. That's the only way out.'",
-        2 => "Note 2: 'Water's gone. Hunger is a sharper edge than any zombie tooth. We need supplies, but the mall is key.'",
-        3 => "Note 3: 'K. was taken. I saw the horde sweep through the tunnels. We must bypass them and find the final beacon.'",
-        4 => "Note 4: 'The safe haven isn't the mall itself—it's the roof. There's a chopper arriving once the signal is fully secured.'",
-        5 => "Note 5: 'Five parts to the signal. Find all five and the rescue frequency will be open. Don't stop. Don't look back.'",
-        _ => "A blank piece of paper. Nothing here.",
-    }
+// --- Inventory Actions ---
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum InventoryAction {
+    Use,
+    Equip,
+    Drop,
 }
 
-fn is_near_building(map: &Map, px: i32, py: i32) -> bool {
-    for dy in -1..=1 {
-        for dx in -1..=1 {
-            if map.in_bounds(px + dx, py + dy) {
-                let idx = map.xy_idx(px + dx, py + dy);
-                if map.tiles[idx] == TileType::Building {
-                    return true;
+/// Applies `action` to the backpack stack at `index` and returns how many
+/// turns the fumbling took; the caller lets the world advance that many turns.
+fn handle_inventory_action(map: &mut Map, state: &mut GameState, index: usize, action: InventoryAction) -> u32 {
+    let Some(stack) = state.inventory.stacks.get(index).copied() else {
+        return 0;
+    };
+    let def = stack.id.def();
+
+    let turns = match action {
+        InventoryAction::Use => match stack.id.use_turns() {
+            Some(turns) if matches!(def.effect, UseEffect::Firebomb(_)) => {
+                if throw_firebomb(map, state, def.effect) {
+                    state.inventory.take_from(index, 1);
+                    turns
+                } else {
+                    0
+                }
+            }
+            Some(turns) => {
+                state.inventory.take_from(index, 1);
+                if let Some(leftover) = stack.id.leaves_behind() {
+                    state.inventory.add(leftover, 1);
+                }
+                state.apply_effect(def.effect);
+                let verb = match def.category {
+                    ItemCategory::Food => "eat",
+                    ItemCategory::Drink => "drink",
+                    _ => "apply",
+                };
+                state.message_log.push(format!("You {} the {}. ({})", verb, def.name, def.effect.describe()));
+                turns
+            }
+            None => {
+                state.message_log.push(format!("You can't use the {} like that.", def.name));
+                0
+            }
+        },
+        InventoryAction::Equip => {
+            if def.category != ItemCategory::Weapon {
+                state.message_log.push(format!("The {} is not a weapon.", def.name));
+                0
+            } else if state.equipped == Some(stack.id) {
+                state.message_log.push(format!("The {} is already in your hand.", def.name));
+                0
+            } else {
+                state.equipped = Some(stack.id);
+                state.message_log.push(format!("You ready the {}.", def.name));
+                1
+            }
+        }
+        InventoryAction::Drop => {
+            if let Some(dropped) = state.inventory.take_from(index, 1) {
+                map.drop_item(state.player_x, state.player_y, dropped);
+                if state.equipped == Some(stack.id) && !state.inventory.has(stack.id) {
+                    state.equipped = None;
                 }
+                state.message_log.push(format!("You drop the {}.", def.name));
             }
+            1
         }
-    }
-    false
-}
-
-fn rest_in_building(state: &mut GameState) {
-    let hunger_cost = 10;
-    let thirst_cost = 10;
-
-    if state.hunger < hunger_cost || state.thirst < thirst_cost {
-        state.message_log.push("Too hungry/thirsty to rest safely!".to_string());
-        return;
-    }
-
-    state.fatigue = state.fatigue.saturating_add(50).min(300); 
-    state.health = state.health.saturating_add(10).min(100);
-    state.hunger = state.hunger.saturating_sub(hunger_cost); 
-    state.thirst = state.thirst.saturating_sub(thirst_cost);
+    };
 
-    state.message_log.push(format!("Rested well! Fatigue: +50, Health: +10. Hunger/Thirst: -{}.", hunger_cost).to_string());
-    
     while state.message_log.len() > 10 { state.message_log.remove(0); }
+    turns
 }
 
-fn handle_tile_interaction(map: &mut Map, state: &mut GameState, x: i32, y: i32, rng: &mut ThreadRng) {
-    if !map.in_bounds(x, y) {
-        state.message_log.push("Bump! You hit the edge of the world.".to_string());
-        return;
+/// Lights a molotov and lobs it at the nearest zombie in sight, burning it and
+/// anything standing next to it. Returns false if nothing was thrown.
+fn throw_firebomb(map: &Map, state: &mut GameState, effect: UseEffect) -> bool {
+    let UseEffect::Firebomb(damage) = effect else { return false };
+    if !state.inventory.has(ItemId::Lighter) {
+        state.message_log.push("You need a lighter to light the molotov.".to_string());
+        return false;
     }
+    let fov = map.compute_fov(state.player_x, state.player_y, state.view_radius());
+    let Some(&(tx, ty)) = targeting::visible_targets(map, &state.zombies, &fov, state.player_x, state.player_y, FIREBOMB_RANGE).first() else {
+        state.message_log.push("No zombie in throwing range.".to_string());
+        return false;
+    };
 
-    if state.zombies.iter().any(|z| z.x == x && z.y == y) {
-        handle_attack(map, state, x, y, rng); 
-        return; 
+    let before = state.zombies.len();
+    for zombie in state.zombies.iter_mut().filter(|z| (z.x - tx).abs() <= 1 && (z.y - ty).abs() <= 1) {
+        zombie.hp = zombie.hp.saturating_sub(damage);
+    }
+    state.zombies.retain(|z| z.hp > 0);
+    let burned = before - state.zombies.len();
+    state.stats.record_kill(ItemId::Molotov.name(), burned as u32);
+    state.make_noise(tx, ty, NoiseKind::Firebomb);
+    state.message_log.push(format!("The molotov bursts into flame! {} zombie(s) burned down.", burned));
+    true
+}
+
+// --- Barricading ---
+
+/// Boards up an adjacent door or window with a barricade kit, preferring
+/// unboarded openings over patching damaged ones. Returns the turns spent.
+fn handle_barricade(map: &mut Map, state: &mut GameState) -> u32 {
+    let (px, py) = (state.player_x, state.player_y);
+    let mut openings: Vec<(i32, i32)> = [(0, -1), (1, 0), (0, 1), (-1, 0)]
+        .iter()
+        .map(|(dx, dy)| (px + dx, py + dy))
+        .filter(|&(x, y)| map.in_bounds(x, y) && matches!(map.tiles[map.xy_idx(x, y)], TileType::Door | TileType::Window))
+        .filter(|&(x, y)| map.barricade_at(x, y).is_none_or(|b| b.is_damaged()))
+        .filter(|&(x, y)| !state.zombies.iter().any(|z| (z.x, z.y) == (x, y)))
+        .collect();
+    openings.sort_by_key(|&(x, y)| map.barricade_at(x, y).map_or(-1, |b| b.hp));
+
+    let turns = match openings.first() {
+        None => {
+            state.message_log.push("There's no open door or window next to you to board up.".to_string());
+            0
+        }
+        Some(_) if !state.inventory.has(ItemId::BarricadeKit) => {
+            state.message_log.push("You need a barricade kit. Craft one from planks and nails (C).".to_string());
+            0
+        }
+        Some(&(x, y)) => {
+            let repair = map.is_barricaded(x, y);
+            state.inventory.remove(ItemId::BarricadeKit, 1);
+            map.board(x, y);
+            state.make_noise(px, py, NoiseKind::Hammering);
+            let opening = if map.tiles[map.xy_idx(x, y)] == TileType::Window { "window" } else { "door" };
+            if repair {
+                state.message_log.push(format!("You nail fresh boards over the damaged {}.", opening));
+            } else {
+                state.message_log.push(format!("You board up the {}. ({} HP)", opening, BARRICADE_HP));
+            }
+            BARRICADE_TURNS
+        }
+    };
+    while state.message_log.len() > 10 { state.message_log.remove(0); }
+    turns
+}
+
+// --- Rainwater ---
+
+/// Holds an empty bottle up to the rain until it fills. Only works outdoors,
+/// i.e. not under one of the map's roofs. Returns the turns spent.
+fn handle_collect_rain(map: &Map, state: &mut GameState) -> u32 {
+    let indoors = map.roofs.iter().any(|r| r.contains(state.player_x, state.player_y));
+    let weather = state.weather.current;
+
+    let turns = if !weather.is_raining() {
+        state.message_log.push("There's no rain to collect.".to_string());
+        0
+    } else if indoors {
+        state.message_log.push("You need to be outside, under the open sky.".to_string());
+        0
+    } else if !state.inventory.remove(ItemId::EmptyBottle, 1) {
+        state.message_log.push("You have no empty bottle to fill.".to_string());
+        0
+    } else {
+        state.inventory.add(ItemId::WaterBottle, 1);
+        state.message_log.push("You fill a bottle with rainwater.".to_string());
+        if weather == Weather::Storm { RAIN_FILL_TURNS / 2 } else { RAIN_FILL_TURNS }
+    };
+    while state.message_log.len() > 10 { state.message_log.remove(0); }
+    turns
+}
+
+// --- Crafting ---
+
+/// Crafts `recipe` if possible and returns the turns it took.
+fn handle_craft(state: &mut GameState, recipe: &crafting::Recipe) -> u32 {
+    let turns = if crafting::craft(recipe, &mut state.inventory) {
+        state.message_log.push(format!("You craft: {} (x{}).", recipe.name, recipe.output.count));
+        recipe.turns
+    } else {
+        state.message_log.push(format!("Can't craft {}: missing {}.", recipe.name, recipe.missing(&state.inventory).join(", ")));
+        0
+    };
+    while state.message_log.len() > 10 { state.message_log.remove(0); }
+    turns
+}
+
+/// Everything that happens after the player spends a turn: new arrivals,
+/// zombie actions and the slow drain on thirst, hunger and fatigue.
+fn advance_world(map: &mut Map, state: &mut GameState, rng: &mut impl Rng) {
+    let phase_before = state.clock.phase();
+    state.clock.advance(clock::MINUTES_PER_TURN);
+    match state.clock.phase() {
+        Phase::Dusk if phase_before == Phase::Day => state.message_log.push("The sun is going down. Find shelter.".to_string()),
+        Phase::Night if phase_before != Phase::Night => state.message_log.push("Night falls. The groaning grows louder...".to_string()),
+        Phase::Dawn if phase_before == Phase::Night => state.message_log.push("First light. You made it through the night.".to_string()),
+        _ => {}
+    }
+    if let Some(weather) = state.weather.tick(rng) {
+        state.message_log.push(match weather {
+            Weather::Clear => "The sky clears.",
+            Weather::Rain => "It starts to rain. Press 'G' to catch some in an empty bottle.",
+            Weather::Fog => "A thick fog rolls in.",
+            Weather::Heatwave => "A heatwave sets in. You'll get thirsty fast.",
+            Weather::Storm => "Thunder rumbles. A storm drowns out every sound.",
+        }.to_string());
+    }
+    // Stragglers drift in from the streets, not out of upstairs bedrooms.
+    if state.level == STREET_LEVEL {
+        spawn_random_zombies(rng, map, state);
+    }
+    update_zombies(map, state, rng);
+    advance_siege(map, state, rng);
+
+    if state.move_counter.is_multiple_of(5) && state.fatigue > 0 { 
+        state.fatigue -= 1;
+    }
+    if state.move_counter.is_multiple_of(state.weather.current.thirst_interval()) && state.thirst > 0 {
+        state.thirst -= 1;
+    }
+    if state.move_counter.is_multiple_of(10) && state.hunger > 0 {
+        state.hunger -= 1;
+    }
+    
+    if state.hunger <= 0 { state.hurt(1, CauseOfDeath::Starvation); }
+    if state.thirst <= 0 { state.hurt(1, CauseOfDeath::Dehydration); }
+    if state.fatigue <= 0 { state.hurt(1, CauseOfDeath::Exhaustion); }
+}
+
+/// Runs the rooftop siege for one turn: waves pour up the mall stairs onto the
+/// roof and, if the player held out long enough, the chopper takes them away.
+fn advance_siege(map: &Map, state: &mut GameState, rng: &mut impl Rng) {
+    let (px, py) = (state.player_x, state.player_y);
+    let Some(siege) = state.siege.as_mut() else {
+        return;
+    };
+    let holding = state.level == UPPER_LEVEL && siege.roof.contains(px, py);
+    let incoming = siege.tick(holding);
+    let (roof, wave, chopper_arrived) = (siege.roof, siege.wave, siege.chopper_arrived());
+
+    if let Some(count) = incoming {
+        let roof_tiles = (roof.y1..=roof.y2).flat_map(|y| (roof.x1..=roof.x2).map(move |x| (x, y)));
+        let (sx, sy) = roof_tiles.clone()
+            .find(|&(x, y)| map.tiles[map.xy_idx(x, y)] == TileType::StairsDown)
+            .unwrap_or(roof.center());
+        // The wave spills out of the stairwell, nearest tiles first.
+        let mut spots: Vec<(i32, i32)> = roof_tiles
+            .filter(|&(x, y)| map.is_walkable(x, y) && (x, y) != (px, py))
+            .filter(|&(x, y)| !state.zombies.iter().any(|z| (z.x, z.y) == (x, y)))
+            .collect();
+        spots.sort_by_key(|&(x, y)| (x - sx).abs() + (y - sy).abs());
+        let room = siege::MAX_SIEGE_ZOMBIES.saturating_sub(state.zombies.len());
+        let climbed = count.min(room).min(spots.len());
+        for &(x, y) in &spots[..climbed] {
+            let mut zombie = Zombie::new(x, y, ZombieKind::roll(rng, state.notes_collected));
+            zombie.awareness = Awareness::Chasing { x: px, y: py };
+            state.zombies.push(zombie);
+        }
+        if climbed > 0 {
+            state.message_log.push(format!("Wave {}: {} of the dead come pounding up the mall stairs!", wave, climbed));
+        }
+    }
+
+    if chopper_arrived && state.outcome.is_none() {
+        state.message_log.push("Rotor wash flattens the dead. The chopper touches down and you're out!".to_string());
+        state.outcome = Some(Outcome::Victory);
+    }
+}
+
+/// Lets the world run for `turns` turns while the player is busy, stopping
+/// early if the run ends.
+fn spend_turns(map: &mut Map, state: &mut GameState, rng: &mut impl Rng, turns: u32) {
+    for _ in 0..turns {
+        state.move_counter += 1;
+        advance_world(map, state, rng);
+        if state.outcome.is_some() {
+            break;
+        }
+    }
+}
+
+fn get_note_content(note_id: u32) -> &'static str {
+    match note_id {
+        1 => "Note 1: 'They followed the sirens. The radio tower signal is strongest near the old shopping center. That's the only way out.'",
+        2 => "Note 2: 'Water's gone. Hunger is a sharper edge than any zombie tooth. We need supplies, but the mall is key.'",
+        3 => "Note 3: 'K. was taken. I saw the horde sweep through the tunnels. We must bypass them and find the final beacon.'",
+        4 => "Note 4: 'The safe haven isn't the mall itself—it's the roof. There's a chopper arriving once the signal is fully secured.'",
+        5 => "Note 5: 'Five parts to the signal. Find all five and the rescue frequency will be open. Don't stop. Don't look back.'",
+        _ => "A blank piece of paper. Nothing here.",
+    }
+}
+
+fn is_near_building(map: &Map, px: i32, py: i32) -> bool {
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            if map.in_bounds(px + dx, py + dy) {
+                let idx = map.xy_idx(px + dx, py + dy);
+                if map.tiles[idx] == TileType::Building {
+                    return true;
+                }
+            }
+        }
+    }
+    false
+}
+
+fn rest_in_building(state: &mut GameState) {
+    let hunger_cost = 10;
+    let thirst_cost = 10;
+
+    if state.hunger < hunger_cost || state.thirst < thirst_cost {
+        state.message_log.push("Too hungry/thirsty to rest safely!".to_string());
+        return;
+    }
+
+    state.fatigue = state.fatigue.saturating_add(50).min(300); 
+    state.health = state.health.saturating_add(10).min(100);
+    state.hunger = state.hunger.saturating_sub(hunger_cost); 
+    state.thirst = state.thirst.saturating_sub(thirst_cost);
+
+    state.clock.advance(REST_MINUTES);
+
+    state.message_log.push(format!("Rested well! Fatigue: +50, Health: +10. Hunger/Thirst: -{}. It's now {}.", hunger_cost, state.clock));
+    
+    while state.message_log.len() > 10 { state.message_log.remove(0); }
+}
+
+fn handle_tile_interaction(map: &mut Map, state: &mut GameState, x: i32, y: i32, rng: &mut impl Rng) {
+    if !map.in_bounds(x, y) {
+        state.message_log.push("Bump! You hit the edge of the world.".to_string());
+        return;
+    }
+
+    if state.zombies.iter().any(|z| z.x == x && z.y == y) {
+        handle_attack(map, state, x, y, rng); 
+        return; 
     }
 
     let idx = map.xy_idx(x, y);
     let tile = map.tiles[idx];
     let mut moved = false;
 
+    if let Some(b) = map.barricade_at(x, y) {
+        state.message_log.push(format!("Boarded up tight. ({}/{} HP)", b.hp, BARRICADE_HP));
+        return;
+    }
+
     match tile {
         TileType::Wall => {
             state.message_log.push("Bump! A solid obstacle.".to_string());
         }
         TileType::Foliage => {
             state.fatigue = state.fatigue.saturating_sub(1);
+            state.make_noise(x, y, NoiseKind::Foliage);
             if rng.gen_bool(0.2) {
                 let found = rng.gen_range(0..3);
                 match found {
-                    0 => { state.message_log.push("Scavenged! Found a moldy berry. (+5 Hunger)".to_string()); state.hunger = state.hunger.saturating_add(5).min(100); }
-                    1 => { state.message_log.push("Scavenged! Found a damp leaf. (+5 Thirst)".to_string()); state.thirst = state.thirst.saturating_add(5).min(100); }
-                    2 => { state.message_log.push("Found a bandage. (+5 Health)".to_string()); state.health = state.health.saturating_add(5).min(100); }
+                    0 => { state.message_log.push("Scavenged! Found a moldy berry.".to_string()); state.inventory.add(ItemId::MoldyBerry, 1); }
+                    1 => { state.message_log.push("Scavenged! Found a damp leaf.".to_string()); state.inventory.add(ItemId::DampLeaf, 1); }
+                    2 => { state.message_log.push("Found a bandage.".to_string()); state.inventory.add(ItemId::Bandage, 1); }
                     _ => {}
                 }
+                state.stats.record_scavenge("Foliage");
                 map.tiles[idx] = TileType::Floor;
             } else {
                 state.message_log.push("Rustle, rustle... just leaves.".to_string());
@@ -691,20 +1348,24 @@ fn handle_tile_interaction(map: &mut Map, state: &mut GameState, x: i32, y: i32,
         }
         TileType::Car => {
             state.fatigue = state.fatigue.saturating_sub(3);
+            state.make_noise(x, y, NoiseKind::CarSearch);
             if rng.gen_bool(0.7) { 
-                let found = rng.gen_range(0..2);
+                let found = rng.gen_range(0..3);
                 match found {
                     0 => { 
-                        if !state.inventory.contains(&"lighter".to_string()) {
+                        if !state.inventory.has(ItemId::Lighter) {
                             state.message_log.push("Car searched. Found a lighter and rags.".to_string()); 
-                            state.inventory.push("lighter".to_string()); 
+                            state.inventory.add(ItemId::Lighter, 1); 
                         } else {
                             state.message_log.push("Car searched. Found some rags.".to_string());
                         }
+                        state.inventory.add(ItemId::Rags, 1);
                     }
-                    1 => { state.message_log.push("Car searched. Found an energy bar! (+10 Hunger)".to_string()); state.hunger = state.hunger.saturating_add(10).min(100); }
+                    1 => { state.message_log.push("Car searched. Found an energy bar!".to_string()); state.inventory.add(ItemId::EnergyBar, 1); }
+                    2 => { state.message_log.push("Car searched. Found a bottle of whiskey under the seat.".to_string()); state.inventory.add(ItemId::Alcohol, 1); }
                     _ => {}
                 }
+                state.stats.record_scavenge("Cars");
                 map.tiles[idx] = TileType::Floor;
             } else {
                 state.message_log.push("Car searched. Nothing useful but rust.".to_string());
@@ -713,12 +1374,15 @@ fn handle_tile_interaction(map: &mut Map, state: &mut GameState, x: i32, y: i32,
         }
         TileType::Resource => {
             state.message_log.push("Dedicated Resource Cache!".to_string());
-            match rng.gen_range(0..3) {
-                0 => { state.message_log.push("Found a water bottle. (+20 Thirst)".to_string()); state.thirst = state.thirst.saturating_add(20).min(100); }
-                1 => { state.message_log.push("Found a can of beans. (+20 Hunger)".to_string()); state.hunger = state.hunger.saturating_add(20).min(100); }
-                2 => { state.message_log.push("Found a first aid kit. (+15 Health)".to_string()); state.health = state.health.saturating_add(15).min(100); }
+            match rng.gen_range(0..5) {
+                0 => { state.message_log.push("Found a water bottle.".to_string()); state.inventory.add(ItemId::WaterBottle, 1); }
+                1 => { state.message_log.push("Found a can of beans.".to_string()); state.inventory.add(ItemId::CanOfBeans, 1); }
+                2 => { state.message_log.push("Found a first aid kit.".to_string()); state.inventory.add(ItemId::FirstAidKit, 1); }
+                3 => { state.message_log.push("Found a couple of planks.".to_string()); state.inventory.add(ItemId::Planks, 2); }
+                4 => { state.message_log.push("Found a box of nails.".to_string()); state.inventory.add(ItemId::Nails, 6); }
                 _ => {}
             }
+            state.stats.record_scavenge("Supply caches");
             map.tiles[idx] = TileType::Floor;
             moved = true;
         }
@@ -727,14 +1391,27 @@ fn handle_tile_interaction(map: &mut Map, state: &mut GameState, x: i32, y: i32,
             moved = true; 
         }
         TileType::Weapon => {
-            if !state.inventory.contains(&"gun".to_string()) {
-                state.message_log.push("Found a **GUN**! (+5 Ammo)".to_string());
-                state.inventory.push("gun".to_string());
-                state.ammo = state.ammo.saturating_add(5);
+            let found = match rng.gen_range(0..10) {
+                0..=2 => ItemId::BaseballBat,
+                3..=4 => ItemId::Crowbar,
+                5 => ItemId::FireAxe,
+                6..=8 => ItemId::Pistol,
+                _ => ItemId::Shotgun,
+            };
+            let ammo = weapons::weapon_def(found).and_then(|w| w.ammo);
+
+            if state.inventory.has(found) && ammo.is_some() {
+                state.message_log.push(format!("Found a {} you already own. Stripped it for ammo.", found.name()));
             } else {
-                state.message_log.push("Found extra ammo.".to_string());
-                state.ammo = state.ammo.saturating_add(5);
+                state.message_log.push(format!("Found a **{}**! Equip it from the backpack (I).", found.name().to_uppercase()));
+                state.inventory.add(found, 1);
+            }
+            if let Some(ammo) = ammo {
+                let rounds = if ammo == ItemId::ShotgunShells { 4 } else { 5 };
+                state.message_log.push(format!("(+{} {})", rounds, ammo.name()));
+                state.inventory.add(ammo, rounds);
             }
+            state.stats.record_scavenge("Weapon caches");
             map.tiles[idx] = TileType::Floor;
             moved = true;
         }
@@ -750,14 +1427,14 @@ fn handle_tile_interaction(map: &mut Map, state: &mut GameState, x: i32, y: i32,
             state.message_log.push(format!("(J) Found Note #{}: {}/5 collected.", note_id, state.notes_collected));
             
             if state.notes_collected == 5 {
-                state.message_log.push(">>> RESCUE SIGNAL SECURED! The Mall is now the objective. <<<".to_string());
+                state.message_log.push(">>> RESCUE SIGNAL SECURED! Get to the mall roof and fire a flare from the helipad. <<<".to_string());
                 
                 if let Some(last_room) = map.rooms.last() {
                     for y in last_room.y1..=last_room.y2 {
                         for x in last_room.x1..=last_room.x2 {
                             if map.in_bounds(x, y) {
                                 let mall_idx = map.xy_idx(x, y);
-                                if map.tiles[mall_idx] != TileType::Wall {
+                                if !matches!(map.tiles[mall_idx], TileType::Wall | TileType::StairsUp) {
                                     map.tiles[mall_idx] = TileType::Mall;
                                 }
                             }
@@ -770,14 +1447,40 @@ fn handle_tile_interaction(map: &mut Map, state: &mut GameState, x: i32, y: i32,
             moved = true;
         }
         TileType::Mall => {
-            state.message_log.push("You reached the Mall! The final challenge awaits...".to_string());
+            state.message_log.push("You reached the Mall! Find the stairs ('<') up to the roof.".to_string());
             moved = true;
-            // WIN CONDITION! Switch to Game Over/Win screen
-            state.health = 0; // Temporary trick to trigger the game end break
         }
-        TileType::Floor => {
+        TileType::Helipad => {
+            moved = true;
+            if state.siege.is_some() {
+                // The flare is already up; nothing to do but hold on.
+            } else if state.notes_collected == 5 {
+                let roof = map.rooms.iter().copied().find(|r| r.contains(x, y)).unwrap_or(Rect::new(x, y, x, y));
+                state.siege = Some(Siege::new(roof));
+                state.message_log.push(format!(
+                    "You fire the flare. Radio: 'Chopper inbound! Hold that roof for {} turns!' Below, the dead start up the mall stairs...",
+                    siege::CHOPPER_TURNS
+                ));
+            } else {
+                state.message_log.push("An empty helipad. Nobody knows you're here yet.".to_string());
+            }
+        }
+        TileType::StairsUp => {
+            state.message_log.push("Stairs lead up. Press '<' to climb.".to_string());
+            moved = true;
+        }
+        TileType::StairsDown => {
+            state.message_log.push("Stairs lead down. Press '>' to descend.".to_string());
+            moved = true;
+        }
+        TileType::Floor | TileType::Door => {
             moved = true; 
         }
+        TileType::Window => {
+            state.fatigue = state.fatigue.saturating_sub(1);
+            state.message_log.push("You climb through the window.".to_string());
+            moved = true;
+        }
         _ => {
             state.message_log.push("Bump! Cannot move there.".to_string());
         }
@@ -786,6 +1489,11 @@ fn handle_tile_interaction(map: &mut Map, state: &mut GameState, x: i32, y: i32,
     if moved {
         state.player_x = x;
         state.player_y = y;
+        state.stats.record_step();
+        for stack in map.take_ground_items(x, y) {
+            state.message_log.push(format!("Picked up {} x{}.", stack.id.name(), stack.count));
+            state.inventory.add_stack(stack);
+        }
         state.fatigue = state.fatigue.saturating_sub(1);
         state.move_counter += 1; 
     }
@@ -795,8 +1503,8 @@ fn handle_tile_interaction(map: &mut Map, state: &mut GameState, x: i32, y: i32,
 
 // --- Rendering Functions ---
 
-fn draw_menu(f: &mut ratatui::Frame, ) {
-    let size = f.size();
+fn draw_menu(f: &mut ratatui::Frame, has_save: bool, map_kind: MapKind, notice: Option<&str>) {
+    let size = f.area();
     let block = Block::default()
         .borders(Borders::ALL)
         .title(Span::styled(" BRAIIIIINNNNSSS!!!!!!! ", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)));
@@ -811,6 +1519,8 @@ fn draw_menu(f: &mut ratatui::Frame, ) {
             Constraint::Length(1),      // Title line 3
             Constraint::Percentage(20), // Middle padding
             Constraint::Length(1),      // Options line
+            Constraint::Length(1),      // Map generator line
+            Constraint::Length(1),      // Notice line (load errors, etc.)
             Constraint::Percentage(30), // Bottom padding
         ].as_ref())
         .split(size);
@@ -831,22 +1541,233 @@ fn draw_menu(f: &mut ratatui::Frame, ) {
         title_style
     ));
 
-    let options_line = Line::from(vec![
+    let mut options = vec![
         Span::styled("Press ", option_style),
         Span::styled("ENTER", option_style.add_modifier(Modifier::BOLD | Modifier::REVERSED)),
         Span::styled(" to START", option_style),
-        Span::styled(" | Press ", option_style),
-        Span::styled("ESC", option_style.add_modifier(Modifier::BOLD | Modifier::REVERSED)),
-        Span::styled(" to QUIT", option_style),
-    ]);
+    ];
+    if has_save {
+        options.push(Span::styled(" | Press ", option_style));
+        options.push(Span::styled("C", option_style.add_modifier(Modifier::BOLD | Modifier::REVERSED)));
+        options.push(Span::styled(" to CONTINUE", option_style));
+    }
+    options.push(Span::styled(" | ", option_style));
+    options.push(Span::styled("H", option_style.add_modifier(Modifier::BOLD | Modifier::REVERSED)));
+    options.push(Span::styled(" HALL OF SURVIVORS", option_style));
+    options.push(Span::styled(" | Press ", option_style));
+    options.push(Span::styled("ESC", option_style.add_modifier(Modifier::BOLD | Modifier::REVERSED)));
+    options.push(Span::styled(" to QUIT", option_style));
+    let options_line = Line::from(options);
 
     f.render_widget(Paragraph::new(title_line1).alignment(ratatui::layout::Alignment::Center), menu_chunks[1]);
     f.render_widget(Paragraph::new(title_line2).alignment(ratatui::layout::Alignment::Center), menu_chunks[2]);
     f.render_widget(Paragraph::new(title_line3).alignment(ratatui::layout::Alignment::Center), menu_chunks[3]);
     f.render_widget(Paragraph::new(options_line).alignment(ratatui::layout::Alignment::Center), menu_chunks[5]);
+
+    let map_line = Line::from(vec![
+        Span::styled(format!("Map: {} ", map_kind.generator().name()), option_style),
+        Span::styled("M", option_style.add_modifier(Modifier::BOLD | Modifier::REVERSED)),
+        Span::styled(" to change", option_style),
+    ]);
+    f.render_widget(Paragraph::new(map_line).alignment(ratatui::layout::Alignment::Center), menu_chunks[6]);
+
+    if let Some(text) = notice {
+        let notice_line = Line::from(Span::styled(text, Style::default().fg(Color::Yellow)));
+        f.render_widget(Paragraph::new(notice_line).alignment(ratatui::layout::Alignment::Center), menu_chunks[7]);
+    }
+}
+
+/// Short HUD label for the weapon in hand, with rounds left for firearms.
+fn held_summary(state: &GameState) -> String {
+    let weapon = weapons::held(state.equipped);
+    match weapon.ammo {
+        Some(ammo) => format!("{} ({})", weapon.name, state.inventory.count(ammo)),
+        None => weapon.name.to_string(),
+    }
+}
+
+fn describe_stack(stack: &ItemStack) -> String {
+    let def = stack.id.def();
+    let durability = match (stack.durability, def.durability) {
+        (Some(left), Some(max)) => format!(", durability {}/{}", left, max),
+        _ => String::new(),
+    };
+    let combat = match weapons::weapon_def(stack.id) {
+        Some(w) => format!(
+            " Hit {:.0}%, dmg {}-{}, reach {}, noise r{}{}.",
+            w.hit_chance * 100.0, w.damage.0, w.damage.1, w.reach, w.noise.radius(),
+            w.ammo.map(|a| format!(", uses {}", a.name())).unwrap_or_default()
+        ),
+        None => String::new(),
+    };
+    format!(
+        "{} ({}): {}. {:.2}kg each{}.{}",
+        def.name, def.category.label(), def.effect.describe(), def.weight, durability, combat
+    )
+}
+
+fn draw_inventory(f: &mut ratatui::Frame, state: &GameState, cursor: usize, detail: Option<&str>) {
+    let area = f.area();
+    let popup_w = 56.min(area.width);
+    let popup_h = (state.inventory.stacks.len() as u16 + 7).max(9).min(area.height);
+    let popup = ratatui::layout::Rect::new(
+        area.x + (area.width - popup_w) / 2,
+        area.y + (area.height - popup_h) / 2,
+        popup_w,
+        popup_h,
+    );
+
+    let mut lines: Vec<Line> = state.inventory.stacks.iter().enumerate().map(|(i, stack)| {
+        let def = stack.id.def();
+        let marker = if state.equipped == Some(stack.id) { "[E] " } else { "    " };
+        let style = if i == cursor {
+            Style::default().fg(Color::Black).bg(Color::Green)
+        } else {
+            Style::default().fg(Color::Green)
+        };
+        Line::from(Span::styled(format!("{}{:<18} x{:<3} {}", marker, def.name, stack.count, def.category.label()), style))
+    }).collect();
+    if lines.is_empty() {
+        lines.push(Line::from(Span::styled("Your backpack is empty.", Style::default().fg(Color::Gray))));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(detail.unwrap_or(""), Style::default().fg(Color::Yellow))));
+    lines.push(Line::from(Span::styled(
+        "E Use | Q Equip | X Drop | ENTER Inspect | ESC Close",
+        Style::default().fg(Color::Cyan),
+    )));
+
+    f.render_widget(ratatui::widgets::Clear, popup);
+    f.render_widget(
+        Paragraph::new(lines)
+            .wrap(ratatui::widgets::Wrap { trim: false })
+            .block(Block::default().borders(Borders::ALL).title(format!(" Backpack ({:.1}kg) ", state.inventory.total_weight()))),
+        popup,
+    );
+}
+
+fn draw_crafting(f: &mut ratatui::Frame, state: &GameState, recipes: &[crafting::Recipe], cursor: usize) {
+    let area = f.area();
+    let popup_w = 64.min(area.width);
+    let popup_h = (recipes.len() as u16 * 2 + 6).max(9).min(area.height);
+    let popup = ratatui::layout::Rect::new(
+        area.x + (area.width - popup_w) / 2,
+        area.y + (area.height - popup_h) / 2,
+        popup_w,
+        popup_h,
+    );
+
+    let mut lines: Vec<Line> = vec![];
+    for (i, recipe) in recipes.iter().enumerate() {
+        let craftable = recipe.can_craft(&state.inventory);
+        let color = if craftable { Color::Green } else { Color::DarkGray };
+        let style = if i == cursor {
+            Style::default().fg(Color::Black).bg(color)
+        } else {
+            Style::default().fg(color)
+        };
+        lines.push(Line::from(Span::styled(
+            format!("{:<26} -> {} x{} ({} turns)", recipe.name, recipe.output.item.name(), recipe.output.count, recipe.turns),
+            style,
+        )));
+        let needs = if craftable {
+            recipe.describe_inputs()
+        } else {
+            format!("{} | missing: {}", recipe.describe_inputs(), recipe.missing(&state.inventory).join(", "))
+        };
+        lines.push(Line::from(Span::styled(format!("    {}", needs), Style::default().fg(Color::Gray))));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("ENTER Craft | W/S Select | ESC Close", Style::default().fg(Color::Cyan))));
+
+    f.render_widget(ratatui::widgets::Clear, popup);
+    f.render_widget(
+        Paragraph::new(lines)
+            .wrap(ratatui::widgets::Wrap { trim: false })
+            .block(Block::default().borders(Borders::ALL).title(" Crafting ")),
+        popup,
+    );
+}
+
+/// Debug overlay (F3): how the layout was put together and where each note
+/// went, so a bad seed can be diagnosed without reading the map dump.
+fn draw_diagnostics(f: &mut ratatui::Frame, world: &World, state: &GameState) {
+    let area = f.area();
+    let popup_w = 64.min(area.width);
+    let popup_h = (world.notes.len() as u16 + 8).max(9).min(area.height);
+    let popup = ratatui::layout::Rect::new(
+        area.x + (area.width - popup_w) / 2,
+        area.y + (area.height - popup_h) / 2,
+        popup_w,
+        popup_h,
+    );
+
+    let street = &world.levels[STREET_LEVEL];
+    let label = Style::default().fg(Color::Gray);
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("{} layout, seed {}, {} rooms (mall is #{})", state.map_kind.generator().name(), state.seed, street.rooms.len(), street.rooms.len().saturating_sub(1)),
+            label,
+        )),
+        Line::from(""),
+        Line::from(Span::styled(format!("{:<6} {:<6} {:<12} {:<8} {}", "Note", "Room", "Tile", "Steps", "Status"), Style::default().fg(Color::Cyan))),
+    ];
+    for (i, note) in world.notes.iter().enumerate() {
+        let waiting = street.in_bounds(note.x, note.y) && street.tiles[street.xy_idx(note.x, note.y)] == TileType::Note;
+        let (status, color) = if waiting { ("waiting", Color::Yellow) } else { ("found", Color::Green) };
+        lines.push(Line::from(Span::styled(
+            format!("{:<6} #{:<5} {:<12} {:<8} {}", i + 1, note.room, format!("({}, {})", note.x, note.y), note.distance, status),
+            Style::default().fg(color),
+        )));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("F3/ESC Close", Style::default().fg(Color::Cyan))));
+
+    f.render_widget(ratatui::widgets::Clear, popup);
+    f.render_widget(
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(" Map Diagnostics ")),
+        popup,
+    );
+}
+
+fn draw_quit_prompt(f: &mut ratatui::Frame) {
+    let area = f.area();
+    let popup_w = 44.min(area.width);
+    let popup_h = 5.min(area.height);
+    let popup = ratatui::layout::Rect::new(
+        area.x + (area.width - popup_w) / 2,
+        area.y + (area.height - popup_h) / 2,
+        popup_w,
+        popup_h,
+    );
+
+    let key_style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD | Modifier::REVERSED);
+    let text_style = Style::default().fg(Color::Cyan);
+    let lines = vec![
+        Line::from(Span::styled("Save before quitting?", text_style)),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("Y", key_style),
+            Span::styled(" Save  ", text_style),
+            Span::styled("N", key_style),
+            Span::styled(" Discard  ", text_style),
+            Span::styled("ESC", key_style),
+            Span::styled(" Back", text_style),
+        ]),
+    ];
+
+    f.render_widget(ratatui::widgets::Clear, popup);
+    f.render_widget(
+        Paragraph::new(lines)
+            .alignment(ratatui::layout::Alignment::Center)
+            .block(Block::default().borders(Borders::ALL).title(" Quit ")),
+        popup,
+    );
 }
 
-fn draw_game(f: &mut ratatui::Frame, game_map: &Map, state: &GameState) {
+fn draw_game(f: &mut ratatui::Frame, game_map: &Map, state: &GameState, fov: &HashMap<(i32, i32), Shade>, aim: Option<(i32, i32)>) {
     let chunks = Layout::default()
         .direction(Direction::Horizontal)
         .margin(1)
@@ -854,7 +1775,7 @@ fn draw_game(f: &mut ratatui::Frame, game_map: &Map, state: &GameState) {
             Constraint::Percentage(80), 
             Constraint::Percentage(20), 
         ].as_ref())
-        .split(f.size());
+        .split(f.area());
 
     let hud_chunks = Layout::default()
         .direction(Direction::Vertical)
@@ -887,15 +1808,22 @@ fn draw_game(f: &mut ratatui::Frame, game_map: &Map, state: &GameState) {
     let view_x_max = view_x_min + render_w;
     let view_y_max = view_y_min + render_h;
     
-    let fov = game_map.compute_fov(state.player_x, state.player_y, 12);
-    let zombie_locations: HashMap<(i32, i32), i32> = state.zombies.iter().map(|z| ((z.x, z.y), z.hp)).collect();
+    let line_of_fire: HashSet<(i32, i32)> = aim
+        .map(|(ax, ay)| targeting::line(state.player_x, state.player_y, ax, ay).into_iter().collect())
+        .unwrap_or_default();
+
+    // Zombies are only drawn while currently in sight.
+    let zombie_locations: HashMap<(i32, i32), &Zombie> = state.zombies.iter()
+        .filter(|z| fov.contains_key(&(z.x, z.y)))
+        .map(|z| ((z.x, z.y), z))
+        .collect();
 
     let mut map_lines = vec![];
     for world_y in view_y_min..view_y_max {
         let mut line = vec![];
         for world_x in view_x_min..view_x_max {
 
-            if !game_map.in_bounds(world_x, world_y) {
+            if !game_map.is_revealed(world_x, world_y) {
                 line.push(Span::styled(" ", Style::default().fg(Color::Black)));
                 continue;
             }
@@ -907,14 +1835,32 @@ fn draw_game(f: &mut ratatui::Frame, game_map: &Map, state: &GameState) {
 
             let c = if world_x == state.player_x && world_y == state.player_y {
                 Span::styled("@", Style::default().fg(Color::Yellow))
-            } else if zombie_locations.contains_key(&tile_pos) {
-                let col = match shade {
-                    Shade::Dark => Color::Rgb(139, 0, 0),
-                    Shade::Dim => Color::Rgb(200, 0, 0),
-                    Shade::Lit => Color::Red,
-                    Shade::Bright => Color::LightRed,
+            } else if let Some(zombie) = zombie_locations.get(&tile_pos) {
+                let col = match (zombie.kind, shade) {
+                    (ZombieKind::Shambler, Shade::Dark) => Color::Rgb(139, 0, 0),
+                    (ZombieKind::Shambler, Shade::Dim) => Color::Rgb(200, 0, 0),
+                    (ZombieKind::Shambler, Shade::Lit) => Color::Red,
+                    (ZombieKind::Shambler, Shade::Bright) => Color::LightRed,
+
+                    (ZombieKind::Sprinter, Shade::Dark | Shade::Dim) => Color::Rgb(200, 110, 0),
+                    (ZombieKind::Sprinter, Shade::Lit | Shade::Bright) => Color::Rgb(255, 150, 0),
+
+                    (ZombieKind::Crawler, Shade::Dark | Shade::Dim) => Color::Rgb(120, 80, 50),
+                    (ZombieKind::Crawler, Shade::Lit | Shade::Bright) => Color::Rgb(170, 120, 70),
+
+                    (ZombieKind::Brute, Shade::Dark | Shade::Dim) => Color::Rgb(140, 40, 180),
+                    (ZombieKind::Brute, Shade::Lit | Shade::Bright) => Color::Rgb(200, 70, 255),
+                };
+                // Lowercase until the zombie has noticed you.
+                let glyph = if zombie.is_alerted() {
+                    zombie.kind.glyph()
+                } else {
+                    zombie.kind.glyph().to_ascii_lowercase()
                 };
-                Span::styled("Z", Style::default().fg(col))
+                Span::styled(glyph.to_string(), Style::default().fg(col))
+            } else if game_map.has_ground_item(world_x, world_y) {
+                let col = if shade == Shade::Dark { Color::Rgb(60, 80, 80) } else { Color::LightCyan };
+                Span::styled("%", Style::default().fg(col))
             } else {
                 let (ch, col) = match (tile, shade) {
                     (TileType::Wall, Shade::Dark) => ('▓', Color::DarkGray),
@@ -922,57 +1868,97 @@ fn draw_game(f: &mut ratatui::Frame, game_map: &Map, state: &GameState) {
                     (TileType::Wall, Shade::Lit) => ('#', Color::White),
                     (TileType::Wall, Shade::Bright) => ('#', Color::Cyan),
                     
-                    (TileType::Floor, Shade::Dark) => (' ', Color::Black),
+                    (TileType::Floor, Shade::Dark) => ('.', Color::Rgb(60, 60, 60)),
                     (TileType::Floor, Shade::Dim) => ('░', Color::DarkGray),
                     (TileType::Floor, Shade::Lit) => ('.', Color::White),
                     (TileType::Floor, Shade::Bright) => ('·', Color::Yellow),
                     
-                    (TileType::Foliage, Shade::Dark) => (' ', Color::Black),
+                    (TileType::Foliage, Shade::Dark) => ('"', Color::Rgb(60, 75, 60)),
                     (TileType::Foliage, Shade::Dim) => ('~', Color::Green),
                     (TileType::Foliage, Shade::Lit) => ('"', Color::LightGreen),
                     (TileType::Foliage, Shade::Bright) => (',', Color::LightGreen),
                     
-                    (TileType::Car, Shade::Dark) => (' ', Color::Black),
+                    (TileType::Car, Shade::Dark) => ('C', Color::Rgb(70, 70, 70)),
                     (TileType::Car, Shade::Dim) => ('C', Color::DarkGray),
                     (TileType::Car, Shade::Lit) => ('C', Color::Gray),
                     (TileType::Car, Shade::Bright) => ('C', Color::White),
                     
-                    (TileType::Resource, Shade::Dark) => (' ', Color::Black),
+                    (TileType::Resource, Shade::Dark) => ('$', Color::Rgb(85, 85, 60)),
                     (TileType::Resource, Shade::Dim) => ('$', Color::Yellow),
                     (TileType::Resource, Shade::Lit) => ('$', Color::LightYellow),
                     (TileType::Resource, Shade::Bright) => ('$', Color::LightYellow),
                     
-                    (TileType::Building, Shade::Dark) => (' ', Color::Black),
+                    (TileType::Building, Shade::Dark) => ('B', Color::Rgb(60, 60, 85)),
                     (TileType::Building, Shade::Dim) => ('B', Color::Blue),
                     (TileType::Building, Shade::Lit) => ('B', Color::Blue),
                     (TileType::Building, Shade::Bright) => ('B', Color::LightBlue),
                     
-                    (TileType::Mall, Shade::Dark) => ('?', Color::Magenta),
+                    (TileType::Mall, Shade::Dark) => ('M', Color::Rgb(90, 60, 90)),
                     (TileType::Mall, Shade::Dim) => ('M', Color::LightMagenta),
                     (TileType::Mall, Shade::Lit) => ('M', Color::LightMagenta),
                     (TileType::Mall, Shade::Bright) => ('M', Color::LightMagenta),
 
-                    (TileType::Weapon, Shade::Dark) => ('?', Color::Red),
+                    (TileType::Weapon, Shade::Dark) => ('W', Color::Rgb(90, 60, 60)),
                     (TileType::Weapon, Shade::Dim) => ('W', Color::Red),
                     (TileType::Weapon, Shade::Lit) => ('W', Color::Red),
                     (TileType::Weapon, Shade::Bright) => ('W', Color::LightRed),
                     
-                    (TileType::Note, Shade::Dark) => (' ', Color::Black),
+                    (TileType::Note, Shade::Dark) => ('!', Color::Rgb(85, 85, 60)),
                     (TileType::Note, Shade::Dim) => ('!', Color::Yellow),
                     (TileType::Note, Shade::Lit) => ('!', Color::LightYellow),
                     (TileType::Note, Shade::Bright) => ('!', Color::White),
                     
+                    (TileType::Door | TileType::Window, _) if game_map.is_barricaded(world_x, world_y) => {
+                        let hp = game_map.barricade_at(world_x, world_y).map_or(0, |b| b.hp);
+                        let col = match shade {
+                            Shade::Dark => Color::Rgb(90, 70, 50),
+                            _ if hp * 2 < BARRICADE_HP => Color::Rgb(200, 120, 60),
+                            _ => Color::Rgb(160, 100, 40),
+                        };
+                        ('▤', col)
+                    }
+                    (TileType::Door, Shade::Dark) => ('+', Color::Rgb(90, 70, 50)),
+                    (TileType::Door, _) => ('+', Color::Rgb(160, 100, 40)),
+                    (TileType::Window, Shade::Dark) => ('=', Color::Rgb(60, 80, 90)),
+                    (TileType::Window, _) => ('=', Color::LightCyan),
+
+                    (TileType::StairsUp, Shade::Dark) => ('<', Color::Rgb(90, 90, 90)),
+                    (TileType::StairsUp, _) => ('<', Color::White),
+                    (TileType::StairsDown, Shade::Dark) => ('>', Color::Rgb(90, 90, 90)),
+                    (TileType::StairsDown, _) => ('>', Color::White),
+                    (TileType::Helipad, Shade::Dark) => ('⊕', Color::Rgb(60, 90, 60)),
+                    (TileType::Helipad, _) => ('⊕', Color::LightGreen),
+
                     (TileType::Zombie, _) => ('Z', Color::Black), 
                 };
                 Span::styled(ch.to_string(), Style::default().fg(col))
             };
+            // Tint the edge of any noise made this turn so its reach is visible.
+            let c = if aim == Some(tile_pos) {
+                c.patch_style(Style::default().bg(Color::Red))
+            } else if line_of_fire.contains(&tile_pos) {
+                c.patch_style(Style::default().bg(Color::Rgb(90, 20, 20)))
+            } else if state.noises.iter().any(|n| n.on_edge(world_x, world_y)) {
+                c.patch_style(Style::default().bg(Color::Rgb(70, 50, 0)))
+            } else {
+                c
+            };
             line.push(c);
         }
         map_lines.push(Line::from(line));
     }
 
-    let title = Block::default().borders(Borders::ALL).title(format!("{} Rooms | WASD/Arrows | R: Rest | T: Retreat | ESC Quit | @ World({},{})", 
-        game_map.rooms.len(), state.player_x, state.player_y));
+    let mut noise_note: String = state.noises.iter()
+        .map(|n| format!(" | *{}* r{}", n.kind.label(), n.radius))
+        .collect();
+    if let Some((ax, ay)) = aim {
+        let weapon = weapons::held(state.equipped);
+        let dist = targeting::distance(state.player_x, state.player_y, ax, ay);
+        let chance = targeting::hit_chance(weapon.hit_chance, dist, weapon.reach);
+        noise_note.push_str(&format!(" | AIM {:.0}% @ {:.1} (TAB cycle, F fire, ESC cancel)", chance * 100.0, dist));
+    }
+    let title = Block::default().borders(Borders::ALL).title(format!("{} Rooms | WASD/Arrows | R: Rest | T: Retreat | I: Bag | C: Craft | B: Board | G: Rain | H: Holster | F: Fire | </>: Stairs | ESC Quit | @ {}({},{}) | Seed {}{}", 
+        game_map.rooms.len(), World::level_name(state.level), state.player_x, state.player_y, state.seed, noise_note));
     let map_widget = Paragraph::new(map_lines).block(title);
     f.render_widget(map_widget, chunks[0]);
 
@@ -990,9 +1976,17 @@ fn draw_game(f: &mut ratatui::Frame, game_map: &Map, state: &GameState) {
             
             let m_ch = if m_x == state.player_x && m_y == state.player_y {
                 Span::styled("@", Color::Yellow)
-            } else if zombie_locations.contains_key(&(m_x, m_y)) {
-                Span::styled("Z", Color::Red)
-            } else if game_map.in_bounds(m_x, m_y) {
+            } else if let Some(zombie) = zombie_locations.get(&(m_x, m_y)) {
+                let col = match zombie.kind {
+                    ZombieKind::Shambler => Color::Red,
+                    ZombieKind::Sprinter => Color::Rgb(255, 150, 0),
+                    ZombieKind::Crawler => Color::Rgb(170, 120, 70),
+                    ZombieKind::Brute => Color::Rgb(200, 70, 255),
+                };
+                Span::styled(zombie.kind.glyph().to_string(), col)
+            } else if game_map.is_revealed(m_x, m_y) && game_map.has_ground_item(m_x, m_y) {
+                Span::styled("%", Color::LightCyan)
+            } else if game_map.is_revealed(m_x, m_y) {
                 let m_tile = game_map.tiles[game_map.xy_idx(m_x, m_y)];
                 match m_tile {
                     TileType::Wall => Span::styled("#", Color::Gray),
@@ -1004,6 +1998,12 @@ fn draw_game(f: &mut ratatui::Frame, game_map: &Map, state: &GameState) {
                     TileType::Mall => Span::styled("M", Color::LightMagenta),
                     TileType::Weapon => Span::styled("W", Color::Red),
                     TileType::Note => Span::styled("!", Color::Yellow),
+                    TileType::Door | TileType::Window if game_map.is_barricaded(m_x, m_y) => Span::styled("▤", Color::Rgb(160, 100, 40)),
+                    TileType::Door => Span::styled("+", Color::Rgb(160, 100, 40)),
+                    TileType::Window => Span::styled("=", Color::LightCyan),
+                    TileType::StairsUp => Span::styled("<", Color::White),
+                    TileType::StairsDown => Span::styled(">", Color::White),
+                    TileType::Helipad => Span::styled("⊕", Color::LightGreen),
                 }
             } else {
                 Span::styled(" ", Color::Black)
@@ -1016,14 +2016,44 @@ fn draw_game(f: &mut ratatui::Frame, game_map: &Map, state: &GameState) {
     f.render_widget(mini_widget, hud_chunks[0]); 
 
     // --- Backpack ---
-    let backpack_items: Vec<Row> = state.inventory.iter().map(|item| {
-        Row::new(vec![Cell::from(Span::styled(item, Style::default().fg(Color::Green)))])
+    let backpack_items: Vec<Row> = state.inventory.stacks.iter().map(|stack| {
+        let def = stack.id.def();
+        let stat = match (stack.durability, def.durability, def.effect) {
+            (Some(left), Some(max), _) => format!("{}/{}", left, max),
+            (_, _, UseEffect::None) => String::new(),
+            (_, _, effect) => effect.describe(),
+        };
+        Row::new(vec![
+            Cell::from(Span::styled(def.name, Style::default().fg(Color::Green))),
+            Cell::from(Span::styled(format!("x{}", stack.count), Style::default().fg(Color::White))),
+            Cell::from(Span::styled(format!("{:.1}kg", stack.weight()), Style::default().fg(Color::Gray))),
+            Cell::from(Span::styled(stat, Style::default().fg(Color::Yellow))),
+        ])
     }).collect();
-    let backpack_table = Table::new(backpack_items, &[Constraint::Percentage(100)]).block(Block::default().borders(Borders::ALL).title(format!("Backpack ({} Ammo)", state.ammo)));
+    let backpack_widths = [Constraint::Min(10), Constraint::Length(4), Constraint::Length(6), Constraint::Length(10)];
+    let backpack_table = Table::new(backpack_items, backpack_widths)
+        .block(Block::default().borders(Borders::ALL).title(format!("Backpack ({:.1}kg) | {}", state.inventory.total_weight(), held_summary(state))));
     f.render_widget(backpack_table, hud_chunks[1]);
 
     // --- Moodles ---
-    let moodle_lines = vec![
+    let phase = state.clock.phase();
+    let phase_color = match phase {
+        Phase::Day => Color::LightYellow,
+        Phase::Dawn | Phase::Dusk => Color::Rgb(255, 150, 0),
+        Phase::Night => Color::LightBlue,
+    };
+    let mut moodle_lines = vec![
+        Line::from(Span::styled(format!("{} ({})", state.clock, phase.label()), phase_color)),
+        Line::from(vec![
+            Span::styled("Weather: ", Color::White),
+            Span::styled(state.weather.current.label(), match state.weather.current {
+                Weather::Clear => Color::LightYellow,
+                Weather::Rain => Color::LightBlue,
+                Weather::Fog => Color::Gray,
+                Weather::Heatwave => Color::LightRed,
+                Weather::Storm => Color::Magenta,
+            }),
+        ]),
         Line::from(vec![
             Span::styled("HP:", Color::Red),
             Span::styled(format!("{:3}", state.health.max(0)), Style::default().fg(if state.health < 25 { Color::Red } else if state.health < 75 { Color::Yellow } else { Color::Green })),
@@ -1041,6 +2071,12 @@ fn draw_game(f: &mut ratatui::Frame, game_map: &Map, state: &GameState) {
             Span::styled(format!("{:3}", state.fatigue.max(0)), Style::default().fg(if state.fatigue < 25 { Color::Red } else if state.fatigue < 77 { Color::Yellow } else { Color::Green })),
         ]),
     ];
+    if let Some(siege) = &state.siege {
+        moodle_lines.insert(2, Line::from(Span::styled(
+            format!("Chopper: {} turns | Wave {}", siege.turns_left, siege.wave),
+            Style::default().fg(Color::LightRed).add_modifier(Modifier::BOLD),
+        )));
+    }
     let moodle_widget = Paragraph::new(moodle_lines).block(Block::default().borders(Borders::ALL).title("Moodles"));
     f.render_widget(moodle_widget, hud_chunks[2]);
 
@@ -1085,16 +2121,16 @@ fn draw_game(f: &mut ratatui::Frame, game_map: &Map, state: &GameState) {
     f.render_widget(dialogue_widget, log_chunks[1]);
 }
 
-fn draw_game_over(f: &mut ratatui::Frame, win: bool) {
-    let size = f.size();
-    let title_style = if win { Style::default().fg(Color::Green).add_modifier(Modifier::BOLD) } 
-                      else { Style::default().fg(Color::Red).add_modifier(Modifier::BOLD) };
-    
-    let message = if win {
-        "YOU ESCAPED! Rescue chopper is here. Project Complete!"
-    } else {
-        "GAME OVER. You succumbed to the elements or the horde."
+fn draw_game_over(f: &mut ratatui::Frame, state: &GameState, replay_notice: Option<&str>) {
+    let size = f.area();
+    let outcome = state.outcome.unwrap_or(Outcome::Quit);
+    let color = match outcome {
+        Outcome::Victory => Color::Green,
+        Outcome::Death(_) => Color::Red,
+        Outcome::Quit => Color::Yellow,
     };
+    let title_style = Style::default().fg(color).add_modifier(Modifier::BOLD);
+    let message = outcome.headline();
 
     let block = Block::default()
         .borders(Borders::ALL)
@@ -1104,8 +2140,11 @@ fn draw_game_over(f: &mut ratatui::Frame, win: bool) {
     let content_chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Percentage(40),
+            Constraint::Percentage(15),
             Constraint::Length(1), 
+            Constraint::Length(1), 
+            Constraint::Length(1), 
+            Constraint::Min(8), 
             Constraint::Length(3), 
         ].as_ref())
         .split(size);
@@ -1114,6 +2153,13 @@ fn draw_game_over(f: &mut ratatui::Frame, win: bool) {
         .style(title_style)
         .alignment(ratatui::layout::Alignment::Center);
 
+    let seed_widget = Paragraph::new(format!(
+        "Survived until {} | Seed: {} on {} (replay with --seed {} --map {})",
+        state.clock, state.seed, state.map_kind.generator().name(), state.seed, state.map_kind.arg()
+    ))
+        .style(Style::default().fg(Color::Gray))
+        .alignment(ratatui::layout::Alignment::Center);
+
     let prompt_widget = Paragraph::new(
         Line::from(vec![
             Span::styled("Press ", Style::default().fg(Color::Cyan)),
@@ -1125,13 +2171,656 @@ fn draw_game_over(f: &mut ratatui::Frame, win: bool) {
     ).alignment(ratatui::layout::Alignment::Center);
 
     f.render_widget(message_widget, content_chunks[1]);
-    f.render_widget(prompt_widget, content_chunks[2]);
+    f.render_widget(seed_widget, content_chunks[2]);
+    if let Some(text) = replay_notice {
+        let notice = Paragraph::new(text)
+            .style(Style::default().fg(Color::Yellow))
+            .alignment(ratatui::layout::Alignment::Center);
+        f.render_widget(notice, content_chunks[3]);
+    }
+    let (summary, height) = run_summary(state, outcome);
+    let mut summary_area = centered_columns(content_chunks[4], 56);
+    summary_area.height = summary_area.height.min(height);
+    f.render_widget(summary, summary_area);
+    f.render_widget(prompt_widget, content_chunks[5]);
+}
+
+/// The end-of-run stats table shown on the game-over screen, and its height.
+fn run_summary(state: &GameState, outcome: Outcome) -> (Table<'static>, u16) {
+    let stats = &state.stats;
+    let heading = Style::default().fg(Color::White).add_modifier(Modifier::BOLD);
+    let detail = Style::default().fg(Color::Gray);
+    let row = |label: String, value: String, style: Style| {
+        Row::new(vec![Cell::from(Span::styled(label, style)), Cell::from(Span::styled(value, style))])
+    };
+
+    let shots = match stats.hit_rate() {
+        Some(rate) => format!("{} ({} hit, {:.0}%)", stats.shots_fired, stats.shots_hit, rate * 100.0),
+        None => "0".to_string(),
+    };
+    let mut rows = vec![
+        row("Fate".to_string(), outcome.fate(), heading),
+        row("Turns survived".to_string(), state.move_counter.to_string(), heading),
+        row("Distance walked".to_string(), format!("{} tiles", stats.distance), heading),
+        row("Notes found".to_string(), format!("{}/5", state.notes_collected), heading),
+        row("Zombies killed".to_string(), stats.total_kills().to_string(), heading),
+    ];
+    rows.extend(stats.kills.iter().map(|(by, n)| row(format!("  {}", by), n.to_string(), detail)));
+    rows.push(row("Shots fired".to_string(), shots, heading));
+    rows.push(row("Places scavenged".to_string(), stats.scavenged.values().sum::<u32>().to_string(), heading));
+    rows.extend(stats.scavenged.iter().map(|(source, n)| row(format!("  {}", source), n.to_string(), detail)));
+    rows.push(row(
+        "Score".to_string(),
+        stats.score(state.move_counter, state.notes_collected, outcome).to_string(),
+        Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+    ));
+
+    let height = rows.len() as u16 + 2;
+    let table = Table::new(rows, [Constraint::Length(20), Constraint::Min(10)])
+        .block(Block::default().borders(Borders::ALL).title(" Run Summary "));
+    (table, height)
+}
+
+/// A horizontally centered strip of `area`, at most `width` columns wide.
+fn centered_columns(area: ratatui::layout::Rect, width: u16) -> ratatui::layout::Rect {
+    let width = width.min(area.width);
+    ratatui::layout::Rect::new(area.x + (area.width - width) / 2, area.y, width, area.height)
+}
+
+/// Status line along the bottom of the screen while a replay plays.
+fn draw_replay_bar(f: &mut ratatui::Frame, playback: &Playback) {
+    let area = f.area();
+    if area.height == 0 {
+        return;
+    }
+    let bar = ratatui::layout::Rect::new(area.x, area.y + area.height - 1, area.width, 1);
+    let status = if playback.is_finished() {
+        "FINISHED"
+    } else if playback.paused {
+        "PAUSED"
+    } else {
+        "PLAYING"
+    };
+    let text = format!(
+        " REPLAY seed {} | key {}/{} | {}x | {} | SPACE Pause  . Step  +/- Speed  ESC Stop ",
+        playback.replay.seed,
+        playback.pos,
+        playback.replay.keys.len(),
+        playback.speed(),
+        status,
+    );
+    f.render_widget(ratatui::widgets::Clear, bar);
+    f.render_widget(Paragraph::new(text).style(Style::default().fg(Color::Black).bg(Color::LightMagenta)), bar);
+}
+
+/// The next key for the game to act on: the recording's during playback,
+/// otherwise whatever the keyboard sent.
+fn next_key(playback: Option<&mut Playback>) -> Result<Option<KeyEvent>> {
+    match playback {
+        Some(pb) => Ok(pb.next_key(Instant::now()).map(KeyEvent::from)),
+        None => match event::read()? {
+            Event::Key(key) => Ok(Some(key)),
+            _ => Ok(None),
+        },
+    }
+}
+
+/// Runs listed in the Hall of Survivors.
+const HALL_SIZE: usize = 10;
+
+/// The best runs on record, with the selected one ready to replay.
+fn draw_hall(f: &mut ratatui::Frame, runs: &[RunRecord], cursor: usize) {
+    let size = f.area();
+    let title_style = Style::default().fg(Color::LightYellow).add_modifier(Modifier::BOLD);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(Span::styled(" HALL OF SURVIVORS ", title_style));
+    f.render_widget(block, size);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Min(4),
+            Constraint::Length(1),
+        ].as_ref())
+        .split(size);
+
+    let header = Row::new(vec!["#", "Score", "Fate", "Turns", "Notes", "Map", "Seed", "Date"])
+        .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD));
+    let rows: Vec<Row> = runs.iter().enumerate().map(|(i, run)| {
+        let color = match run.outcome {
+            Outcome::Victory => Color::Green,
+            Outcome::Death(_) => Color::Red,
+            Outcome::Quit => Color::Yellow,
+        };
+        let style = if i == cursor {
+            Style::default().fg(Color::Black).bg(color)
+        } else {
+            Style::default().fg(color)
+        };
+        Row::new(vec![
+            (i + 1).to_string(),
+            run.score.to_string(),
+            run.outcome.fate(),
+            run.turns.to_string(),
+            format!("{}/5", run.notes),
+            run.map_kind.generator().name().to_string(),
+            run.seed.to_string(),
+            run.date(),
+        ]).style(style)
+    }).collect();
+    let widths = [
+        Constraint::Length(3),
+        Constraint::Length(6),
+        Constraint::Min(22),
+        Constraint::Length(6),
+        Constraint::Length(6),
+        Constraint::Length(7),
+        Constraint::Length(21),
+        Constraint::Length(11),
+    ];
+
+    if runs.is_empty() {
+        let empty = Paragraph::new("No runs on record yet. Go make some history.")
+            .style(Style::default().fg(Color::Gray))
+            .alignment(ratatui::layout::Alignment::Center);
+        f.render_widget(empty, chunks[1]);
+    } else {
+        f.render_widget(Table::new(rows, widths).header(header), chunks[1]);
+    }
+
+    let help = Paragraph::new("ENTER Replay seed | W/S Select | ESC Back")
+        .style(Style::default().fg(Color::Cyan))
+        .alignment(ratatui::layout::Alignment::Center);
+    f.render_widget(help, chunks[2]);
+}
+
+// --- Command Line ---
+
+/// Reads `--seed <u64>` from the command line. When present, every run started
+/// from the menu uses that seed, so a map can be replayed exactly.
+/// `--map <bsp|town>` picks the layout the menu starts on.
+/// `--replay <file>` skips the menu and plays back a recorded run instead.
+/// Anything else is an error, so a typo never quietly starts a random run.
+#[derive(Default)]
+struct CliArgs {
+    seed: Option<u64>,
+    map_kind: Option<MapKind>,
+    replay: Option<PathBuf>,
+}
+
+fn parse_args() -> Result<CliArgs> {
+    let mut cli = CliArgs::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--seed" {
+            let value = args.next().ok_or_else(|| eyre!("--seed requires a value"))?;
+            let seed = value.parse::<u64>().map_err(|_| eyre!("invalid seed '{}': expected an unsigned integer", value))?;
+            cli.seed = Some(seed);
+        } else if arg == "--map" {
+            let value = args.next().ok_or_else(|| eyre!("--map requires a value"))?;
+            let kind = MapKind::from_arg(&value).ok_or_else(|| eyre!("invalid map '{}': expected 'bsp' or 'town'", value))?;
+            cli.map_kind = Some(kind);
+        } else if arg == "--replay" {
+            let value = args.next().ok_or_else(|| eyre!("--replay requires a file"))?;
+            cli.replay = Some(PathBuf::from(value));
+        } else {
+            return Err(eyre!("unknown argument '{}': expected --seed, --map or --replay", arg));
+        }
+    }
+    Ok(cli)
+}
+
+// --- Input ---
+
+/// Everything a key press can touch: the run itself and the state of the
+/// screens around it.
+struct App {
+    current_mode: AppMode,
+    world: World,
+    state: GameState,
+    // Every RNG consumer draws from this single stream, seeded once per run.
+    rng: ChaCha8Rng,
+    // The live run was continued from the save file, so it owns that file.
+    from_save: bool,
+    recipes: Vec<crafting::Recipe>,
+    cli_seed: Option<u64>,
+    map_kind: MapKind,
+    menu_notice: Option<String>,
+    hall_runs: Vec<RunRecord>,
+    hall_cursor: usize,
+    fov: HashMap<(i32, i32), Shade>,
+    inv_cursor: usize,
+    inv_detail: Option<String>,
+    targets: Vec<(i32, i32)>,
+    target_cursor: usize,
+    craft_cursor: usize,
+    // Appends the live run's keys to its replay file.
+    recorder: Option<Recorder>,
+    replay_notice: Option<String>,
+    // Set while a recorded run is being watched rather than played.
+    playback: Option<Playback>,
+}
+
+/// Whether the main loop carries on after a key.
+enum Flow {
+    Continue,
+    Exit,
+}
+
+impl App {
+    fn new(recipes: Vec<crafting::Recipe>, cli_seed: Option<u64>, map_kind: MapKind) -> Self {
+        App {
+            current_mode: AppMode::Menu,
+            world: World::new(vec![Map::new(160, 47)]), // Initial empty map
+            state: GameState::new(0),
+            rng: ChaCha8Rng::seed_from_u64(0),
+            from_save: false,
+            recipes,
+            cli_seed,
+            map_kind,
+            menu_notice: None,
+            hall_runs: vec![],
+            hall_cursor: 0,
+            fov: HashMap::new(),
+            inv_cursor: 0,
+            inv_detail: None,
+            targets: vec![],
+            target_cursor: 0,
+            craft_cursor: 0,
+            recorder: None,
+            replay_notice: None,
+            playback: None,
+        }
+    }
+
+    fn in_run(&self) -> bool {
+        matches!(self.current_mode, AppMode::Game | AppMode::Inventory | AppMode::Targeting | AppMode::Crafting | AppMode::Diagnostics | AppMode::QuitPrompt)
+    }
+
+    /// Recomputes what the player sees; runs before every frame, so the
+    /// next key acts on the same view the player was shown.
+    fn refresh_view(&mut self) {
+        if self.in_run() {
+            let game_map = &mut self.world.levels[self.state.level];
+            self.fov = game_map.compute_fov(self.state.player_x, self.state.player_y, self.state.view_radius());
+            game_map.reveal(&self.fov);
+        }
+    }
+}
+
+/// Opens the replay file for a run that is starting; if that fails the run
+/// goes unrecorded and the game-over screen says why.
+fn start_recording(seed: u64, map_kind: MapKind) -> (Option<Recorder>, Option<String>) {
+    match Recorder::start(&history::data_dir().join("replays"), seed, map_kind, history::now()) {
+        Ok(recorder) => (Some(recorder), None),
+        Err(err) => (None, Some(format!("Replay not recorded: {}", err))),
+    }
+}
+
+/// Applies one key to whichever screen is up.
+fn handle_key(app: &mut App, key: KeyEvent) -> Flow {
+    let App {
+        current_mode, world, state, rng, from_save, recipes, cli_seed, map_kind, menu_notice, hall_runs, hall_cursor,
+        fov, inv_cursor, inv_detail, targets, target_cursor, craft_cursor, recorder, replay_notice, playback,
+    } = app;
+    let game_map = &mut world.levels[state.level];
+    match *current_mode {
+        
+        AppMode::Menu => {
+            match key.code {
+                KeyCode::Enter => {
+                    // Transition to Game Mode and initialize everything
+                    let seed = cli_seed.unwrap_or_else(|| rand::thread_rng().gen());
+                    match start_run(seed, *map_kind) {
+                        Ok((new_world, new_state, new_rng)) => {
+                            (*world, *state, *rng) = (new_world, new_state, new_rng);
+                            *from_save = false;
+                            (*recorder, *replay_notice) = start_recording(seed, *map_kind);
+                            *menu_notice = None;
+                            *current_mode = AppMode::Game;
+                        }
+                        Err(e) => *menu_notice = Some(format!("Could not build a map: {}", e)),
+                    }
+                },
+                KeyCode::Char('m') => *map_kind = map_kind.next(),
+                KeyCode::Char('h') => {
+                    match history::load() {
+                        Ok(history) => {
+                            *hall_runs = history.top(HALL_SIZE);
+                            *hall_cursor = 0;
+                            *current_mode = AppMode::Hall;
+                        }
+                        Err(err) => *menu_notice = Some(format!("Could not open the Hall of Survivors: {}", err)),
+                    }
+                },
+                KeyCode::Char('c') if save::save_exists() => {
+                    match save::load_game() {
+                        Ok((saved_world, saved_state, saved_rng)) => {
+                            *world = saved_world;
+                            *state = saved_state;
+                            *rng = saved_rng;
+                            *from_save = true;
+                            // A replay has to start from the seed, so continued runs go unrecorded.
+                            *recorder = None;
+                            *replay_notice = None;
+                            *menu_notice = None;
+                            *current_mode = AppMode::Game;
+                        }
+                        Err(err) => *menu_notice = Some(format!("Could not continue: {}", err)),
+                    }
+                },
+                KeyCode::Esc => return Flow::Exit,
+                _ => {}
+            }
+        },
+        
+        AppMode::Game => {
+            if key.code == KeyCode::Esc {
+                *current_mode = AppMode::QuitPrompt;
+                return Flow::Continue;
+            }
+            
+            let mut action_taken = false;
+            state.noises.clear();
+            
+            if key.code == KeyCode::Char('r') {
+                if is_near_building(game_map, state.player_x, state.player_y) {
+                    rest_in_building(state);
+                    action_taken = true;
+                } else {
+                    state.message_log.push("You can only rest inside or near a Building ('B').".to_string());
+                }
+            } 
+            else if key.code == KeyCode::Char('t') {
+                handle_retreat_action(game_map, state, rng);
+                action_taken = true;
+                state.move_counter += 1; 
+            }
+            else if key.code == KeyCode::Char('f') {
+                let weapon = weapons::held(state.equipped);
+                if !weapon.is_firearm() {
+                    state.message_log.push("Equip a firearm to aim (H draws one).".to_string());
+                    return Flow::Continue;
+                }
+                *targets = targeting::visible_targets(game_map, &state.zombies, fov, state.player_x, state.player_y, weapon.reach);
+                if targets.is_empty() {
+                    state.message_log.push("No zombies in your line of fire.".to_string());
+                    return Flow::Continue;
+                }
+                *target_cursor = 0;
+                *current_mode = AppMode::Targeting;
+                return Flow::Continue;
+            }
+            else if key.code == KeyCode::Char('h') {
+                toggle_holster(state);
+            }
+            else if key.code == KeyCode::Char('i') {
+                *inv_cursor = 0;
+                *inv_detail = None;
+                *current_mode = AppMode::Inventory;
+                return Flow::Continue;
+            }
+            else if key.code == KeyCode::Char('c') {
+                *craft_cursor = 0;
+                *current_mode = AppMode::Crafting;
+                return Flow::Continue;
+            }
+            else if key.code == KeyCode::F(3) {
+                *current_mode = AppMode::Diagnostics;
+                return Flow::Continue;
+            }
+            else if matches!(key.code, KeyCode::Char('<') | KeyCode::Char('>')) {
+                let turns = use_stairs(world, state, key.code == KeyCode::Char('<'));
+                spend_turns(&mut world.levels[state.level], state, rng, turns);
+                if state.outcome.is_some() {
+                    *current_mode = AppMode::GameOver;
+                }
+                return Flow::Continue;
+            }
+            else if key.code == KeyCode::Char('g') {
+                let turns = handle_collect_rain(game_map, state);
+                spend_turns(game_map, state, rng, turns);
+                if state.outcome.is_some() {
+                    *current_mode = AppMode::GameOver;
+                }
+                return Flow::Continue;
+            }
+            else if key.code == KeyCode::Char('b') {
+                let turns = handle_barricade(game_map, state);
+                spend_turns(game_map, state, rng, turns);
+                if state.outcome.is_some() {
+                    *current_mode = AppMode::GameOver;
+                }
+                return Flow::Continue;
+            }
+            else if key.code == KeyCode::Char('j') { 
+                state.message_log.push("The journal is already visible in the bottom-right panel.".to_string());
+                action_taken = true;
+            }
+
+            let (target_x, target_y) = match key.code {
+                KeyCode::Up | KeyCode::Char('w') => (state.player_x, state.player_y - 1),
+                KeyCode::Down | KeyCode::Char('s') => (state.player_x, state.player_y + 1),
+                KeyCode::Left | KeyCode::Char('a') => (state.player_x - 1, state.player_y),
+                KeyCode::Right | KeyCode::Char('d') => (state.player_x + 1, state.player_y),
+                _ => {
+                    if !action_taken { return Flow::Continue; } 
+                    else { (state.player_x, state.player_y) }
+                }
+            };
+
+            let turn_taken = if !action_taken {
+                let old_x = state.player_x;
+                let old_y = state.player_y;
+                
+                handle_tile_interaction(game_map, state, target_x, target_y, rng);
+                
+                (state.player_x != old_x || state.player_y != old_y) || 
+                    (target_x == old_x && target_y == old_y && 
+                     state.zombies.iter().any(|z| z.x == target_x && z.y == target_y))
+            } else {
+                action_taken
+            };
+            
+            // The interaction itself may have ended the run (rescue or a bite)
+            if state.outcome.is_some() {
+                *current_mode = AppMode::GameOver;
+                return Flow::Continue;
+            }
+            
+            // Game Turn Logic
+            if turn_taken {
+                advance_world(game_map, state, rng);
+
+                if state.outcome.is_some() {
+                    *current_mode = AppMode::GameOver;
+                }
+            }
+        },
+
+        AppMode::Targeting => {
+            match key.code {
+                KeyCode::Esc => *current_mode = AppMode::Game,
+                KeyCode::Tab | KeyCode::Right | KeyCode::Down | KeyCode::Char('d') | KeyCode::Char('s') => {
+                    *target_cursor = (*target_cursor + 1) % targets.len();
+                }
+                KeyCode::BackTab | KeyCode::Left | KeyCode::Up | KeyCode::Char('a') | KeyCode::Char('w') => {
+                    *target_cursor = (*target_cursor + targets.len() - 1) % targets.len();
+                }
+                KeyCode::Enter | KeyCode::Char('f') => {
+                    let (tx, ty) = targets[*target_cursor];
+                    state.noises.clear();
+                    handle_attack(game_map, state, tx, ty, rng);
+                    *current_mode = AppMode::Game;
+
+                    state.move_counter += 1;
+                    advance_world(game_map, state, rng);
+                    if state.outcome.is_some() {
+                        *current_mode = AppMode::GameOver;
+                    }
+                }
+                _ => {}
+            }
+        },
+
+        AppMode::Inventory => {
+            let stack_count = state.inventory.stacks.len();
+            let action = match key.code {
+                KeyCode::Esc | KeyCode::Char('i') => {
+                    *current_mode = AppMode::Game;
+                    None
+                }
+                KeyCode::Up | KeyCode::Char('w') => {
+                    *inv_cursor = inv_cursor.saturating_sub(1);
+                    *inv_detail = None;
+                    None
+                }
+                KeyCode::Down | KeyCode::Char('s') => {
+                    if *inv_cursor + 1 < stack_count { *inv_cursor += 1; }
+                    *inv_detail = None;
+                    None
+                }
+                KeyCode::Enter => {
+                    *inv_detail = state.inventory.stacks.get(*inv_cursor).map(describe_stack);
+                    None
+                }
+                KeyCode::Char('e') => Some(InventoryAction::Use),
+                KeyCode::Char('q') => Some(InventoryAction::Equip),
+                KeyCode::Char('x') => Some(InventoryAction::Drop),
+                _ => None,
+            };
+
+            if let Some(action) = action {
+                state.noises.clear();
+                let turns = handle_inventory_action(game_map, state, *inv_cursor, action);
+                *inv_cursor = (*inv_cursor).min(state.inventory.stacks.len().saturating_sub(1));
+                *inv_detail = None;
+
+                // Zombies keep coming while you rummage through the bag.
+                spend_turns(game_map, state, rng, turns);
+                if state.outcome.is_some() {
+                    *current_mode = AppMode::GameOver;
+                }
+            }
+        },
+        
+        AppMode::Crafting => {
+            match key.code {
+                KeyCode::Esc | KeyCode::Char('c') => *current_mode = AppMode::Game,
+                KeyCode::Up | KeyCode::Char('w') => *craft_cursor = craft_cursor.saturating_sub(1),
+                KeyCode::Down | KeyCode::Char('s') if *craft_cursor + 1 < recipes.len() => *craft_cursor += 1,
+                KeyCode::Enter => {
+                    if let Some(recipe) = recipes.get(*craft_cursor) {
+                        state.noises.clear();
+                        let turns = handle_craft(state, recipe);
+                        spend_turns(game_map, state, rng, turns);
+                        if state.outcome.is_some() {
+                            *current_mode = AppMode::GameOver;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        },
+
+        AppMode::Diagnostics => {
+            if matches!(key.code, KeyCode::Esc | KeyCode::F(3)) {
+                *current_mode = AppMode::Game;
+            }
+        },
+
+        AppMode::QuitPrompt => {
+            match key.code {
+                // A watched run ends at the prompt; it never writes over the player's save.
+                KeyCode::Char('y') if playback.is_some() => {
+                    *playback = None;
+                    *current_mode = AppMode::Menu;
+                },
+                KeyCode::Char('y') => {
+                    if let Err(err) = save::save_game(world, state, rng) {
+                        state.message_log.push(format!("Save failed: {}", err));
+                        *current_mode = AppMode::Game;
+                        return Flow::Continue;
+                    }
+                    return Flow::Exit;
+                },
+                KeyCode::Char('n') => {
+                    state.outcome = Some(Outcome::Quit);
+                    *current_mode = AppMode::GameOver;
+                },
+                KeyCode::Esc => *current_mode = AppMode::Game,
+                _ => {}
+            }
+        },
+
+        AppMode::Hall => {
+            match key.code {
+                KeyCode::Esc => *current_mode = AppMode::Menu,
+                KeyCode::Up | KeyCode::Char('w') => *hall_cursor = hall_cursor.saturating_sub(1),
+                KeyCode::Down | KeyCode::Char('s') if *hall_cursor + 1 < hall_runs.len() => *hall_cursor += 1,
+                KeyCode::Enter => {
+                    if let Some(run) = hall_runs.get(*hall_cursor) {
+                        *map_kind = run.map_kind;
+                        match start_run(run.seed, run.map_kind) {
+                            Ok((new_world, new_state, new_rng)) => {
+                                (*world, *state, *rng) = (new_world, new_state, new_rng);
+                                *from_save = false;
+                                (*recorder, *replay_notice) = start_recording(run.seed, run.map_kind);
+                                *menu_notice = None;
+                                *current_mode = AppMode::Game;
+                            }
+                            Err(e) => {
+                                *menu_notice = Some(format!("Could not build a map: {}", e));
+                                *current_mode = AppMode::Menu;
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        },
+
+        AppMode::GameOver => {
+            match key.code {
+                KeyCode::Enter => {
+                    // Reset to main menu
+                    *current_mode = AppMode::Menu;
+                },
+                KeyCode::Esc => return Flow::Exit,
+                _ => {}
+            }
+        }
+    }
+    Flow::Continue
 }
 
 // --- Main function ---
 
 fn main() -> Result<()> {
     color_eyre::install()?;
+    // The replay is already on disk; put the terminal back so the report
+    // that follows is readable.
+    let report_panic = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), LeaveAlternateScreen);
+        report_panic(info);
+    }));
+    let cli = parse_args()?;
+    let recipes = crafting::load_recipes()?;
+    // Load and build a replay's run before touching the terminal, so a bad
+    // file is reported on a sane screen.
+    let replay_start = match cli.replay.as_deref() {
+        Some(path) => {
+            let replay = Replay::load(path)?;
+            let run = start_run(replay.seed, replay.map_kind)?;
+            Some((replay, run))
+        }
+        None => None,
+    };
 
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -1139,147 +2828,95 @@ fn main() -> Result<()> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut rng = rand::thread_rng();
-    let mut current_mode = AppMode::Menu;
-    let mut game_map = Map::new(160, 47); // Initial empty map
-    let mut state = GameState::new();
-    let mut win_condition_met = false;
+    let mut app = App::new(recipes, cli.seed, cli.map_kind.unwrap_or(MapKind::Bsp));
+    let mut previous_mode = app.current_mode;
     
     // Initial map and state generation when switching from Menu to Game
     // We don't generate the first map here, but inside the loop once 'Game' mode starts.
-
+    if let Some((replay, (new_world, new_state, new_rng))) = replay_start {
+        app.map_kind = replay.map_kind;
+        (app.world, app.state, app.rng) = (new_world, new_state, new_rng);
+        app.playback = Some(Playback::new(replay, Instant::now()));
+        app.current_mode = AppMode::Game;
+    }
 
     loop {
+        // --- RUN END ---
+        // A run that just ended spends the save it was continued from, goes
+        // into the history and has its replay closed. A replay is a re-watched run: none of that.
+        if app.current_mode == AppMode::GameOver && previous_mode != AppMode::GameOver && app.playback.is_none() {
+            if app.from_save && app.state.outcome != Some(Outcome::Quit) {
+                save::delete_save();
+            }
+            app.menu_notice = record_run(&app.state);
+            if let Some(recorder) = app.recorder.take() {
+                app.replay_notice = Some(format!("Replay saved; watch it with --replay {}", recorder.path.display()));
+            }
+        }
+        previous_mode = app.current_mode;
+
         // --- RENDERING ---
+        app.refresh_view();
+        let App { current_mode, world, state, recipes, map_kind, menu_notice, hall_runs, hall_cursor, fov, inv_cursor, inv_detail, targets, target_cursor, craft_cursor, replay_notice, playback, .. } = &app;
+        let game_map = &world.levels[state.level];
         terminal.draw(|f| {
             match current_mode {
-                AppMode::Menu => draw_menu(f),
-                AppMode::Game => draw_game(f, &game_map, &state),
-                AppMode::GameOver => draw_game_over(f, win_condition_met),
+                AppMode::Menu => draw_menu(f, save::save_exists(), *map_kind, menu_notice.as_deref()),
+                AppMode::Game => draw_game(f, game_map, state, fov, None),
+                AppMode::Targeting => draw_game(f, game_map, state, fov, targets.get(*target_cursor).copied()),
+                AppMode::Inventory => {
+                    draw_game(f, game_map, state, fov, None);
+                    draw_inventory(f, state, *inv_cursor, inv_detail.as_deref());
+                }
+                AppMode::Crafting => {
+                    draw_game(f, game_map, state, fov, None);
+                    draw_crafting(f, state, recipes, *craft_cursor);
+                }
+                AppMode::Diagnostics => {
+                    draw_game(f, game_map, state, fov, None);
+                    draw_diagnostics(f, world, state);
+                }
+                AppMode::QuitPrompt => {
+                    draw_game(f, game_map, state, fov, None);
+                    draw_quit_prompt(f);
+                }
+                AppMode::GameOver => draw_game_over(f, state, replay_notice.as_deref()),
+                AppMode::Hall => draw_hall(f, hall_runs, *hall_cursor),
+            }
+            if let Some(playback) = playback {
+                draw_replay_bar(f, playback);
             }
         })?;
 
         // --- INPUT HANDLING ---
-        if event::poll(std::time::Duration::from_millis(150))? {
-            if let Event::Key(key) = event::read()? {
-                
-                match current_mode {
-                    
-                    AppMode::Menu => {
-                        match key.code {
-                            KeyCode::Enter => {
-                                // Transition to Game Mode and initialize everything
-                                let (new_map, new_state) = init_game(&mut rng);
-                                game_map = new_map;
-                                state = new_state;
-                                win_condition_met = false;
-                                current_mode = AppMode::Game;
-                            },
-                            KeyCode::Esc => break, 
-                            _ => {}
-                        }
-                    },
-                    
-                    AppMode::Game => {
-                        let mut turn_taken = false;
-                        
-                        if key.code == KeyCode::Esc { break; } // Quit from game
-                        
-                        let mut action_taken = false;
-                        
-                        if key.code == KeyCode::Char('r') {
-                            if is_near_building(&game_map, state.player_x, state.player_y) {
-                                rest_in_building(&mut state);
-                                action_taken = true;
-                            } else {
-                                state.message_log.push("You can only rest inside or near a Building ('B').".to_string());
-                            }
-                        } 
-                        else if key.code == KeyCode::Char('t') {
-                            handle_retreat_action(&game_map, &mut state, &mut rng);
-                            action_taken = true;
-                            state.move_counter += 1; 
-                        }
-                        else if key.code == KeyCode::Char('j') { 
-                            state.message_log.push("The journal is already visible in the bottom-right panel.".to_string());
-                            action_taken = true;
-                        }
-
-                        let (target_x, target_y) = match key.code {
-                            KeyCode::Up | KeyCode::Char('w') => (state.player_x, state.player_y - 1),
-                            KeyCode::Down | KeyCode::Char('s') => (state.player_x, state.player_y + 1),
-                            KeyCode::Left | KeyCode::Char('a') => (state.player_x - 1, state.player_y),
-                            KeyCode::Right | KeyCode::Char('d') => (state.player_x + 1, state.player_y),
-                            _ => {
-                                if !action_taken { continue; } 
-                                else { (state.player_x, state.player_y) }
-                            }
-                        };
-
-                        if !action_taken {
-                            let old_x = state.player_x;
-                            let old_y = state.player_y;
-                            
-                            handle_tile_interaction(&mut game_map, &mut state, target_x, target_y, &mut rng);
-                            
-                            turn_taken = (state.player_x != old_x || state.player_y != old_y) || 
-                                         (target_x == old_x && target_y == old_y && 
-                                          state.zombies.iter().any(|z| z.x == target_x && z.y == target_y));
-                        } else {
-                            turn_taken = action_taken;
-                        }
-                        
-                        // Check for win condition after interaction (Mall interaction sets health to 0)
-                        if state.health <= 0 && state.notes_collected == 5 && target_x == state.player_x && target_y == state.player_y && game_map.tiles[game_map.xy_idx(target_x, target_y)] == TileType::Mall {
-                            win_condition_met = true;
-                            current_mode = AppMode::GameOver;
+        let ready = match app.playback.as_mut() {
+            // The viewer's keys steer the playback; the game only sees the recording.
+            Some(pb) => {
+                if event::poll(pb.wait(Instant::now()))? {
+                    if let Event::Key(key) = event::read()? {
+                        if key.code == KeyCode::Esc {
+                            app.playback = None;
+                            app.current_mode = AppMode::Menu;
                             continue;
                         }
-                        
-                        // Check for general death/loss condition
-                        if state.health <= 0 {
-                            win_condition_met = false;
-                            current_mode = AppMode::GameOver;
-                            continue;
-                        }
-                        
-                        // Game Turn Logic
-                        if turn_taken {
-                            spawn_random_zombies(&mut rng, &game_map, &mut state);
-                            update_zombies(&game_map, &mut state);
-
-                            if state.move_counter % 5 == 0 { 
-                                if state.fatigue > 0 { state.fatigue -= 1; }
-                                if state.thirst > 0 { state.thirst -= 1; }
-                            }
-                            if state.move_counter % 10 == 0 {
-                                if state.hunger > 0 { state.hunger -= 1; }
-                            }
-                            
-                            if state.hunger <= 0 || state.thirst <= 0 || state.fatigue <= 0 {
-                                 if state.hunger <= 0 { state.health = state.health.saturating_sub(1); }
-                                 if state.thirst <= 0 { state.health = state.health.saturating_sub(1); }
-                                 if state.fatigue <= 0 { state.health = state.health.saturating_sub(1); }
-
-                                 if state.health <= 0 {
-                                     win_condition_met = false;
-                                     current_mode = AppMode::GameOver;
-                                 }
-                            }
-                        }
-                    },
-                    
-                    AppMode::GameOver => {
-                        match key.code {
-                            KeyCode::Enter => {
-                                // Reset to main menu
-                                current_mode = AppMode::Menu;
-                            },
-                            KeyCode::Esc => break,
-                            _ => {}
-                        }
+                        pb.control(key.code);
                     }
                 }
+                true
+            }
+            None => event::poll(std::time::Duration::from_millis(150))?,
+        };
+        if ready {
+            if let Some(key) = next_key(app.playback.as_mut())? {
+                if app.in_run() {
+                    if let Some(Err(err)) = app.recorder.as_mut().map(|recorder| recorder.record(key.code)) {
+                        app.recorder = None;
+                        app.replay_notice = Some(format!("Replay stopped: {}", err));
+                    }
+                }
+                if let Flow::Exit = handle_key(&mut app, key) {
+                    break;
+                }
             }
         }
     }
@@ -1291,6 +2928,237 @@ fn main() -> Result<()> {
     )?;
     terminal.show_cursor()?;
 
-    println!("\nProject Concluded. Final Mode: {:?}", current_mode);
+    println!("\nProject Concluded. Final Mode: {:?}", app.current_mode);
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_at(x: i32, y: i32) -> GameState {
+        let mut state = GameState::new(0);
+        (state.player_x, state.player_y) = (x, y);
+        state
+    }
+
+    #[test]
+    fn sprinters_act_twice_a_turn_and_crawlers_every_other_turn() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let mut map = Map::from_ascii(&["...................."]);
+        let mut state = state_at(0, 0);
+        for kind in [ZombieKind::Sprinter, ZombieKind::Crawler, ZombieKind::Shambler] {
+            let mut zombie = Zombie::new(19, 0, kind);
+            zombie.awareness = Awareness::Chasing { x: 0, y: 0 };
+            state.zombies.push(zombie);
+            update_zombies(&mut map, &mut state, &mut rng);
+            update_zombies(&mut map, &mut state, &mut rng);
+            let zombie = state.zombies.pop().unwrap();
+            let expected = match kind {
+                ZombieKind::Sprinter => 15,
+                ZombieKind::Crawler => 18,
+                _ => 17,
+            };
+            assert_eq!(zombie.x, expected, "{} after two turns", kind.name());
+        }
+    }
+
+    #[test]
+    fn tougher_zombies_turn_up_more_as_notes_are_found() {
+        let mut rng = ChaCha8Rng::seed_from_u64(8);
+        let mut dangerous = |notes| {
+            (0..2000)
+                .map(|_| ZombieKind::roll(&mut rng, notes))
+                .filter(|k| matches!(k, ZombieKind::Sprinter | ZombieKind::Brute))
+                .count()
+        };
+        let early = dangerous(0);
+        let late = dangerous(5);
+        assert!(early < 300, "about 1 in 9 at the start, got {} of 2000", early);
+        assert!(late > 900, "about half by the last note, got {} of 2000", late);
+        assert_eq!(ZombieKind::Brute.spawn_weight(9), ZombieKind::Brute.spawn_weight(5));
+    }
+
+    #[test]
+    fn weapons_wear_down_and_break() {
+        let mut state = GameState::new(0);
+        state.inventory.add(ItemId::RustyKnife, 1);
+        state.inventory.stacks[0].durability = Some(2);
+
+        wear_equipped_weapon(&mut state);
+        assert_eq!(state.inventory.stacks[0].durability, Some(1));
+        wear_equipped_weapon(&mut state);
+        assert_eq!(state.inventory.count(ItemId::RustyKnife), 1);
+        assert_eq!(state.equipped, Some(ItemId::RustyKnife), "the spare knife is still in hand");
+
+        let spare = state.inventory.stacks.iter().position(|s| s.id == ItemId::RustyKnife).unwrap();
+        state.inventory.stacks[spare].durability = Some(1);
+        wear_equipped_weapon(&mut state);
+        assert!(!state.inventory.has(ItemId::RustyKnife));
+        assert_eq!(state.equipped, None);
+    }
+
+    #[test]
+    fn holstering_falls_back_to_the_best_melee_weapon() {
+        let mut state = GameState::new(0);
+        state.inventory.add(ItemId::Pistol, 1);
+        state.inventory.add(ItemId::BaseballBat, 1);
+        state.equipped = Some(ItemId::Pistol);
+
+        toggle_holster(&mut state);
+        assert_eq!(state.equipped, Some(ItemId::BaseballBat));
+        toggle_holster(&mut state);
+        assert_eq!(state.equipped, Some(ItemId::Pistol));
+
+        state.inventory.remove(ItemId::BaseballBat, 1);
+        state.inventory.remove(ItemId::RustyKnife, 1);
+        toggle_holster(&mut state);
+        assert_eq!(state.equipped, None, "with no melee weapon left it's fists");
+    }
+
+    #[test]
+    fn walls_block_a_zombies_sight() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let mut open = Map::from_ascii(&["..........", ".........."]);
+        let mut walled = Map::from_ascii(&["....#.....", "....#....."]);
+
+        for (map, alerted) in [(&mut open, true), (&mut walled, false)] {
+            let mut state = state_at(0, 0);
+            state.zombies.push(Zombie::new(7, 0, ZombieKind::Shambler));
+            update_zombies(map, &mut state, &mut rng);
+            assert_eq!(state.zombies[0].is_alerted(), alerted);
+        }
+    }
+
+    #[test]
+    fn zombies_hear_the_player_through_walls_only_up_close() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let mut map = Map::from_ascii(&[".#......"]);
+        let hearing = ZombieKind::Shambler.hearing_radius();
+
+        let mut state = state_at(0, 0);
+        state.zombies.push(Zombie::new(hearing, 0, ZombieKind::Shambler));
+        state.zombies.push(Zombie::new(hearing + 2, 0, ZombieKind::Shambler));
+        update_zombies(&mut map, &mut state, &mut rng);
+        assert!(state.zombies[0].is_alerted());
+        assert!(!state.zombies[1].is_alerted());
+    }
+
+    #[test]
+    fn zombies_walk_the_long_way_round_to_a_gunshot() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let mut map = Map::from_ascii(&[
+            "##################",
+            "#................#",
+            "###############.##",
+            "#................#",
+            "##################",
+        ]);
+        let mut state = state_at(16, 1);
+        state.zombies.push(Zombie::new(1, 3, ZombieKind::Shambler));
+        state.make_noise(1, 1, NoiseKind::Gunshot);
+
+        update_zombies(&mut map, &mut state, &mut rng);
+        let zombie = &state.zombies[0];
+        assert_eq!(zombie.awareness, Awareness::Investigating { x: 1, y: 1 });
+        assert_eq!((zombie.x, zombie.y), (2, 3), "the only way there is through the gap");
+    }
+
+    #[test]
+    fn a_lost_trail_is_followed_then_given_up() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let mut map = Map::from_ascii(&["......#.......", "......#......."]);
+        let mut state = state_at(13, 0);
+        let mut zombie = Zombie::new(1, 0, ZombieKind::Shambler);
+        zombie.awareness = Awareness::Chasing { x: 2, y: 0 };
+        state.zombies.push(zombie);
+
+        update_zombies(&mut map, &mut state, &mut rng);
+        let zombie = &state.zombies[0];
+        assert_eq!(zombie.awareness, Awareness::Investigating { x: 2, y: 0 });
+        assert_eq!((zombie.x, zombie.y), (2, 0));
+
+        update_zombies(&mut map, &mut state, &mut rng);
+        assert_eq!(state.zombies[0].awareness, Awareness::Idle);
+    }
+
+    /// Plays a run from `seed` through `handle_key`, the way the main loop
+    /// does, until the keys run out or the run ends.
+    fn play(seed: u64, keys: &[KeyCode]) -> App {
+        let mut app = App::new(crafting::load_recipes().unwrap(), None, MapKind::Bsp);
+        (app.world, app.state, app.rng) = start_run(seed, MapKind::Bsp).unwrap();
+        app.current_mode = AppMode::Game;
+        for &code in keys {
+            if app.current_mode == AppMode::GameOver {
+                break;
+            }
+            app.refresh_view();
+            handle_key(&mut app, KeyEvent::from(code));
+        }
+        app
+    }
+
+    #[test]
+    fn the_same_keys_on_the_same_seed_replay_the_same_run() {
+        // Everything but Esc, which would lead off to the quit prompt and a save.
+        let pool = [
+            KeyCode::Char('w'), KeyCode::Char('a'), KeyCode::Char('s'), KeyCode::Char('d'),
+            KeyCode::Up, KeyCode::Down, KeyCode::Left, KeyCode::Right,
+            KeyCode::Char('t'), KeyCode::Char('r'), KeyCode::Char('g'), KeyCode::Char('b'),
+            KeyCode::Char('h'), KeyCode::Char('f'), KeyCode::Tab, KeyCode::Enter,
+            KeyCode::Char('i'), KeyCode::Char('e'), KeyCode::Char('q'), KeyCode::Char('x'),
+            KeyCode::Char('c'), KeyCode::Char('<'), KeyCode::Char('>'), KeyCode::F(3),
+        ];
+        let mut picker = ChaCha8Rng::seed_from_u64(99);
+        let keys: Vec<KeyCode> = (0..600).map(|_| pool[picker.gen_range(0..pool.len())]).collect();
+
+        let first = play(7, &keys);
+        let second = play(7, &keys);
+        assert!(first.state.move_counter > 20, "the keys should have played some turns");
+        assert_eq!(first.current_mode, second.current_mode);
+        assert_eq!(serde_json::to_string(&first.state).unwrap(), serde_json::to_string(&second.state).unwrap());
+        assert_eq!(serde_json::to_string(&first.world).unwrap(), serde_json::to_string(&second.world).unwrap());
+        assert_eq!(serde_json::to_string(&first.rng).unwrap(), serde_json::to_string(&second.rng).unwrap());
+    }
+
+    #[test]
+    fn a_watched_run_stops_at_the_quit_prompt_without_saving() {
+        let before = std::fs::read(save::SAVE_PATH).ok();
+        let keys = vec![replay::ReplayKey::Esc, replay::ReplayKey::Char('y')];
+        let mut app = play(7, &[]);
+        app.playback = Some(Playback::new(Replay { seed: 7, map_kind: MapKind::Bsp, keys }, Instant::now()));
+
+        assert!(matches!(handle_key(&mut app, KeyEvent::from(KeyCode::Esc)), Flow::Continue));
+        assert_eq!(app.current_mode, AppMode::QuitPrompt);
+        assert!(matches!(handle_key(&mut app, KeyEvent::from(KeyCode::Char('y'))), Flow::Continue));
+        assert_eq!(app.current_mode, AppMode::Menu);
+        assert!(app.playback.is_none());
+        assert_eq!(std::fs::read(save::SAVE_PATH).ok(), before, "the save file was touched");
+    }
+
+    fn catches_rain(map: &Map, x: i32, y: i32) -> bool {
+        let mut state = state_at(x, y);
+        state.weather.current = Weather::Rain;
+        state.inventory.add(ItemId::EmptyBottle, 1);
+        handle_collect_rain(map, &mut state) > 0
+    }
+
+    #[test]
+    fn rain_is_caught_on_the_mall_roof_and_in_open_ruins_but_not_under_a_roof() {
+        for kind in [MapKind::Bsp, MapKind::Town] {
+            let (world, _, _) = start_run(3, kind).unwrap();
+            let (street, upper) = (&world.levels[STREET_LEVEL], &world.levels[UPPER_LEVEL]);
+            let (mx, my) = street.rooms.last().unwrap().center();
+            assert!(catches_rain(upper, mx, my), "{:?}: the mall roof is open to the sky", kind);
+            assert!(!catches_rain(street, mx, my), "{:?}: the mall hall is under its roof", kind);
+
+            // A room with nothing built above it.
+            let single = street.rooms[1..street.rooms.len() - 1]
+                .iter()
+                .find(|r| upper.tiles[upper.xy_idx(r.x1, r.y1)] == TileType::Wall)
+                .unwrap();
+            let open = kind == MapKind::Bsp;
+            assert_eq!(catches_rain(street, single.x1, single.y1), open, "{:?}: ruins are roofless, houses are not", kind);
+        }
+    }
+}
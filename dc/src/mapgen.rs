@@ -0,0 +1,750 @@
+// --- Map Generators ---
+//
+// Every layout comes from a `MapGenerator`, which hands back the map along
+// with where the player starts, which room is the mall and where the notes
+// lie. The first room is the spawn room and the last room is always the mall.
+// Both generators hand note placement to `place_notes`, which spreads the
+// notes over distinct rooms along the walking distance from the spawn.
+// The level above (house upper floors and the mall roof) is built as part of
+// the same attempt. Nothing leaves here unvalidated: `generate_valid`
+// flood-fills each attempt and rolls a fresh one if anything the run needs,
+// down to the helipad on the roof, is out of reach.
+// `MapKind` is the menu/CLI-facing choice between generators.
+
+use crate::pathfinding::FlowField;
+use crate::{Map, Rect, TileType, Zombie, ZombieKind};
+use color_eyre::eyre::{eyre, Result};
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+/// How many layouts to try before giving up on a seed.
+const MAX_ATTEMPTS: u32 = 20;
+pub const NOTE_COUNT: usize = 5;
+/// Chance that a house (any room but the first and last) has an upper floor.
+const UPPER_FLOOR_CHANCE: f64 = 0.35;
+
+/// Where one note went and why; listed by the in-game diagnostics view.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotePlacement {
+    pub x: i32,
+    pub y: i32,
+    /// Index into `map.rooms`.
+    pub room: usize,
+    /// Steps from the spawn to the note.
+    pub distance: u32,
+}
+
+pub struct GeneratedMap {
+    pub map: Map,
+    /// The level above `map`, linked to it by stairs.
+    pub upper: Map,
+    /// Occupants of the upper floors, parked until the player climbs up.
+    pub upper_zombies: Vec<Zombie>,
+    pub spawn: (i32, i32),
+    /// Floor of the mall; also the last entry of `map.rooms`.
+    pub mall: Rect,
+    pub notes: Vec<(i32, i32)>,
+    pub placements: Vec<NotePlacement>,
+}
+
+/// A floor tile and its walking distance from the spawn.
+type Spot = (i32, i32, u32);
+
+/// Puts up to `NOTE_COUNT` notes in distinct rooms, never the spawn room or
+/// the mall. Rooms reachable from the spawn are ordered by walking distance
+/// and cut into `NOTE_COUNT` bands; each band gets one note, so the trail
+/// leads steadily further out. Placements come back nearest first.
+pub fn place_notes(map: &mut Map, rng: &mut dyn RngCore) -> Vec<NotePlacement> {
+    let Some((sx, sy)) = map.rooms.first().map(|r| r.center()) else {
+        return vec![];
+    };
+    let field = FlowField::from_target(map, sx, sy, u32::MAX);
+
+    // (room, nearest distance, bare reachable floor tiles)
+    let mut candidates: Vec<(usize, u32, Vec<Spot>)> = vec![];
+    for (i, room) in map.rooms.iter().enumerate().take(map.rooms.len().saturating_sub(1)).skip(1) {
+        let tiles: Vec<Spot> = (room.y1..=room.y2)
+            .flat_map(|y| (room.x1..=room.x2).map(move |x| (x, y)))
+            .filter(|&(x, y)| map.in_bounds(x, y) && map.tiles[map.xy_idx(x, y)] == TileType::Floor)
+            .filter_map(|(x, y)| field.distance(x, y).map(|d| (x, y, d)))
+            .collect();
+        if let Some(nearest) = tiles.iter().map(|t| t.2).min() {
+            candidates.push((i, nearest, tiles));
+        }
+    }
+    candidates.sort_by_key(|c| c.1);
+
+    let bands = NOTE_COUNT.min(candidates.len());
+    let mut placements = vec![];
+    for band in 0..bands {
+        let start = band * candidates.len() / bands;
+        let end = (band + 1) * candidates.len() / bands;
+        let (room, _, tiles) = &candidates[rng.gen_range(start..end)];
+        let (x, y, distance) = tiles[rng.gen_range(0..tiles.len())];
+        let idx = map.xy_idx(x, y);
+        map.tiles[idx] = TileType::Note;
+        placements.push(NotePlacement { x, y, room: *room, distance });
+    }
+    placements
+}
+
+impl GeneratedMap {
+    /// Wraps a finished map, reading the spawn, mall and notes off it, and
+    /// builds the level above it.
+    fn from_map(mut map: Map, placements: Vec<NotePlacement>, rng: &mut dyn RngCore) -> Self {
+        // The player always lands on bare floor. Notes never go in the spawn
+        // room, so this only ever clears scenery.
+        let spawn = map.rooms.first().map_or((1, 1), |r| r.center());
+        if map.in_bounds(spawn.0, spawn.1) {
+            let idx = map.xy_idx(spawn.0, spawn.1);
+            map.tiles[idx] = TileType::Floor;
+        }
+        let mall = map.rooms.last().copied().unwrap_or(Rect::new(0, 0, 0, 0));
+        let notes = (0..map.height as i32)
+            .flat_map(|y| (0..map.width as i32).map(move |x| (x, y)))
+            .filter(|&(x, y)| map.tiles[map.xy_idx(x, y)] == TileType::Note)
+            .collect();
+        let (upper, upper_zombies) = Self::build_upper_level(&mut map, rng);
+        GeneratedMap { map, upper, upper_zombies, spawn, mall, notes, placements }
+    }
+
+    /// Gives some houses an upper floor and the mall (the last room) a roof
+    /// with a helipad in the middle. Each gets a staircase at the same spot on
+    /// both levels, and the room below is roofed over; the upper floors come
+    /// with a little loot and maybe an occupant, and a roof of their own.
+    fn build_upper_level(ground: &mut Map, rng: &mut dyn RngCore) -> (Map, Vec<Zombie>) {
+        let mut upper = Map::new(ground.width, ground.height);
+        let mut zombies = vec![];
+        let last = ground.rooms.len().saturating_sub(1);
+
+        for (i, room) in ground.rooms.clone().into_iter().enumerate().skip(1) {
+            let is_mall = i == last;
+            if !is_mall && !rng.gen_bool(UPPER_FLOOR_CHANCE) {
+                continue;
+            }
+            // Stairs go on a bare floor tile so they never bury a note or
+            // cache, and never under the helipad.
+            let pad = room.center();
+            let Some((sx, sy)) = (room.y1..=room.y2)
+                .flat_map(|y| (room.x1..=room.x2).map(move |x| (x, y)))
+                .find(|&(x, y)| ground.tiles[ground.xy_idx(x, y)] == TileType::Floor && !(is_mall && (x, y) == pad))
+            else {
+                continue;
+            };
+
+            let idx = ground.xy_idx(sx, sy);
+            ground.tiles[idx] = TileType::StairsUp;
+            if !ground.roofs.iter().any(|r| r.contains(sx, sy)) {
+                ground.roofs.push(room);
+            }
+            upper.apply_room(&room);
+            upper.rooms.push(room);
+            upper.tiles[idx] = TileType::StairsDown;
+
+            if is_mall {
+                let pad = upper.xy_idx(pad.0, pad.1);
+                upper.tiles[pad] = TileType::Helipad;
+                continue;
+            }
+            upper.roofs.push(room);
+            for _ in 0..rng.gen_range(1..=2) {
+                let (x, y) = (rng.gen_range(room.x1..=room.x2), rng.gen_range(room.y1..=room.y2));
+                let loot = upper.xy_idx(x, y);
+                if upper.tiles[loot] == TileType::Floor {
+                    upper.tiles[loot] = if rng.gen_bool(0.2) { TileType::Weapon } else { TileType::Resource };
+                }
+            }
+            if rng.gen_bool(0.5) {
+                let (x, y) = room.center();
+                if upper.tiles[upper.xy_idx(x, y)] == TileType::Floor {
+                    zombies.push(Zombie::new(x, y, ZombieKind::roll(rng, 0)));
+                }
+            }
+        }
+        (upper, zombies)
+    }
+
+    /// Checks that the spawn, every note and the mall are mutually reachable,
+    /// that the mall is not the room the player starts in, and that its stairs
+    /// lead up to a helipad.
+    pub fn validate(&self) -> Result<(), String> {
+        let map = &self.map;
+        let (sx, sy) = self.spawn;
+        if !map.is_walkable(sx, sy) {
+            return Err(format!("spawn ({}, {}) is not walkable", sx, sy));
+        }
+        if map.rooms.len() < 2 {
+            return Err(format!("only {} room(s); the mall needs a room of its own", map.rooms.len()));
+        }
+        if self.notes.len() != NOTE_COUNT {
+            return Err(format!("{} notes placed, expected {}", self.notes.len(), NOTE_COUNT));
+        }
+        let mall = &self.mall;
+        if let Some(&(x, y)) = self.notes.iter().find(|&&(x, y)| x >= mall.x1 && x <= mall.x2 && y >= mall.y1 && y <= mall.y2) {
+            return Err(format!("note at ({}, {}) is inside the mall", x, y));
+        }
+
+        // Moves are reversible, so one flood from the spawn covers every pair.
+        let field = FlowField::from_target(map, sx, sy, u32::MAX);
+        if let Some(&(x, y)) = self.notes.iter().find(|&&(x, y)| field.distance(x, y).is_none()) {
+            return Err(format!("note at ({}, {}) is unreachable", x, y));
+        }
+        let mall_reachable = (mall.y1..=mall.y2).any(|y| (mall.x1..=mall.x2).any(|x| field.distance(x, y).is_some()));
+        if !mall_reachable {
+            return Err("the mall is unreachable".to_string());
+        }
+
+        let Some((ux, uy)) = (mall.y1..=mall.y2)
+            .flat_map(|y| (mall.x1..=mall.x2).map(move |x| (x, y)))
+            .find(|&(x, y)| map.tiles[map.xy_idx(x, y)] == TileType::StairsUp && field.distance(x, y).is_some())
+        else {
+            return Err("the mall has no reachable stairs up to the roof".to_string());
+        };
+        let roof = FlowField::from_target(&self.upper, ux, uy, u32::MAX);
+        let helipad = (mall.y1..=mall.y2)
+            .flat_map(|y| (mall.x1..=mall.x2).map(move |x| (x, y)))
+            .any(|(x, y)| self.upper.tiles[self.upper.xy_idx(x, y)] == TileType::Helipad && roof.distance(x, y).is_some());
+        if !helipad {
+            return Err("the mall roof has no helipad to reach".to_string());
+        }
+        Ok(())
+    }
+}
+
+pub trait MapGenerator {
+    fn name(&self) -> &'static str;
+    fn generate(&self, width: usize, height: usize, rng: &mut dyn RngCore) -> GeneratedMap;
+}
+
+/// Generates layouts until one passes validation.
+pub fn generate_valid(generator: &dyn MapGenerator, width: usize, height: usize, rng: &mut dyn RngCore) -> Result<GeneratedMap> {
+    let mut last_error = String::new();
+    for _ in 0..MAX_ATTEMPTS {
+        let generated = generator.generate(width, height, rng);
+        match generated.validate() {
+            Ok(()) => return Ok(generated),
+            Err(err) => last_error = err,
+        }
+    }
+    Err(eyre!("{} map generation failed {} times; last problem: {}", generator.name(), MAX_ATTEMPTS, last_error))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MapKind {
+    Bsp,
+    Town,
+}
+
+impl MapKind {
+    pub fn generator(self) -> &'static dyn MapGenerator {
+        match self {
+            MapKind::Bsp => &BspGenerator,
+            MapKind::Town => &TownGenerator,
+        }
+    }
+
+    /// The value accepted by `--map`.
+    pub fn arg(self) -> &'static str {
+        match self {
+            MapKind::Bsp => "bsp",
+            MapKind::Town => "town",
+        }
+    }
+
+    pub fn from_arg(arg: &str) -> Option<Self> {
+        [MapKind::Bsp, MapKind::Town].into_iter().find(|k| k.arg() == arg)
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            MapKind::Bsp => MapKind::Town,
+            MapKind::Town => MapKind::Bsp,
+        }
+    }
+}
+
+/// The original rooms-and-corridors layout. Its rooms are roofless ruins;
+/// only those given a floor above end up under cover.
+pub struct BspGenerator;
+
+impl BspGenerator {
+    fn carve(map: &mut Map, rng: &mut dyn RngCore) {
+        let mut rects = vec![Rect::new(1, 1, map.width as i32 - 2, map.height as i32 - 2)];
+
+        while !rects.is_empty() && map.rooms.len() < 20 {
+            let idx = rng.gen_range(0..rects.len());
+            let current = rects.swap_remove(idx);
+            let rects_before = rects.len();
+
+            if current.x2 - current.x1 >= 12 && current.y2 - current.y1 >= 12 {
+                let h_split = rng.gen_bool(0.5);
+                let split_dim_size = if h_split { current.y2 - current.y1 } else { current.x2 - current.x1 };
+                if split_dim_size > 8 {
+                    let split = if h_split {
+                        rng.gen_range(current.y1 + 4..current.y2 - 4)
+                    } else {
+                        rng.gen_range(current.x1 + 4..current.x2 - 4)
+                    };
+
+                    let child1 = if h_split {
+                        Rect::new(current.x1, current.y1, current.x2, split)
+                    } else {
+                        Rect::new(current.x1, current.y1, split, current.y2)
+                    };
+
+                    let child2 = if h_split {
+                        Rect::new(current.x1, split, current.x2, current.y2)
+                    } else {
+                        Rect::new(split, current.y1, current.x2, current.y2)
+                    };
+
+                    if child1.x2 - child1.x1 >= 6 && child1.y2 - child1.y1 >= 6 {
+                        rects.push(child1);
+                    }
+                    if child2.x2 - child2.x1 >= 6 && child2.y2 - child2.y1 >= 6 {
+                        rects.push(child2);
+                    }
+                }
+            }
+
+            // Only leaves get a room; carving one in a rect that was split
+            // would swallow all of its children.
+            if rects.len() == rects_before {
+                let room = current.create_room(rng);
+                let valid = !map.rooms.iter().any(|r| room.intersects(r));
+                if valid {
+                    map.apply_room(&room); 
+                    map.rooms.push(room);       
+                }
+            }
+        }
+
+        for i in 1..map.rooms.len() {
+            let (prev_x, prev_y) = map.rooms[i - 1].center();
+            let (curr_x, curr_y) = map.rooms[i].center();
+            
+            if rng.gen_bool(0.5) {
+                map.apply_h_tunnel(prev_x, curr_x, prev_y);
+                map.apply_v_tunnel(curr_y, prev_y, curr_x);
+            } else {
+                map.apply_v_tunnel(prev_y, curr_y, prev_x);
+                map.apply_h_tunnel(curr_x, prev_x, curr_y);
+            }
+
+            // Tunnel Zombie
+            if i == 1 {
+                let (z_x, z_y) = if prev_x != curr_x {
+                    ((prev_x + curr_x) / 2, prev_y)
+                } else {
+                    (prev_x, (prev_y + curr_y) / 2)
+                };
+                if map.in_bounds(z_x, z_y) {
+                    let idx = map.xy_idx(z_x, z_y);
+                    if map.tiles[idx] == TileType::Floor {
+                         map.tiles[idx] = TileType::Zombie; 
+                    }
+                }
+            }
+        }
+        
+        Self::place_openings(map, rng);
+
+        // --- Static Population & Feature Generation ---
+        for y in 0..map.height as i32 {
+            for x in 0..map.width as i32 {
+                let idx = map.xy_idx(x, y);
+                
+                if map.tiles[idx] != TileType::Floor {
+                    continue;
+                }
+                
+                // 1. Place FEATURES (Foliage, Car, etc.)
+                if rng.gen_bool(0.08) { 
+                    match rng.gen_range(0..25) { 
+                        0..=10 => map.tiles[idx] = TileType::Foliage,
+                        11..=17 => map.tiles[idx] = TileType::Car,
+                        18..=20 => map.tiles[idx] = TileType::Resource,
+                        21..=23 => map.tiles[idx] = TileType::Building,
+                        24 => map.tiles[idx] = TileType::Weapon, 
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        // 2. Clear the last room of Mall/other tiles (will be set later)
+        if map.rooms.len() > 1 {
+            if let Some(last_room) = map.rooms.last() {
+                for y in last_room.y1..=last_room.y2 {
+                    for x in last_room.x1..=last_room.x2 {
+                        if map.in_bounds(x, y) {
+                            let idx = map.xy_idx(x, y);
+                            if map.tiles[idx] != TileType::Wall {
+                                map.tiles[idx] = TileType::Floor;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Turns one-tile gaps in room walls (where a corridor enters) into doors,
+    /// and the odd wall between a room and open floor outside into a window.
+    fn place_openings(map: &mut Map, rng: &mut dyn RngCore) {
+        for room in map.rooms.clone() {
+            let mut edge: Vec<((i32, i32), (i32, i32))> = vec![];
+            for x in room.x1..=room.x2 {
+                edge.push(((x, room.y1 - 1), (0, -1)));
+                edge.push(((x, room.y2 + 1), (0, 1)));
+            }
+            for y in room.y1..=room.y2 {
+                edge.push(((room.x1 - 1, y), (-1, 0)));
+                edge.push(((room.x2 + 1, y), (1, 0)));
+            }
+
+            for ((x, y), (ox, oy)) in edge {
+                if !map.in_bounds(x, y) {
+                    continue;
+                }
+                let idx = map.xy_idx(x, y);
+                // Neighbours along the wall, on either side of this tile.
+                let (s1, s2) = ((x - oy.abs(), y - ox.abs()), (x + oy.abs(), y + ox.abs()));
+                let is_wall = |(sx, sy): (i32, i32)| !map.is_passable(sx, sy);
+
+                if map.tiles[idx] == TileType::Floor && is_wall(s1) && is_wall(s2) {
+                    map.tiles[idx] = TileType::Door;
+                } else if map.tiles[idx] == TileType::Wall
+                    && map.in_bounds(x + ox, y + oy)
+                    && map.tiles[map.xy_idx(x + ox, y + oy)] == TileType::Floor
+                    && rng.gen_bool(0.05)
+                {
+                    map.tiles[idx] = TileType::Window;
+                }
+            }
+        }
+    }
+
+}
+
+impl MapGenerator for BspGenerator {
+    fn name(&self) -> &'static str {
+        "Ruins"
+    }
+
+    fn generate(&self, width: usize, height: usize, rng: &mut dyn RngCore) -> GeneratedMap {
+        let mut map = Map::new(width, height);
+        Self::carve(&mut map, rng);
+        let placements = place_notes(&mut map, rng);
+        GeneratedMap::from_map(map, placements, rng)
+    }
+}
+
+// --- Suburb ---
+
+/// Width of every street, in tiles.
+const STREET: i32 = 3;
+/// Size of the lots between streets.
+const BLOCK_W: i32 = 24;
+const BLOCK_H: i32 = 11;
+/// Narrowest lot a house is built on.
+const MIN_LOT_W: i32 = 10;
+
+/// A grid of streets lined with parked cars, blocks of houses with overgrown
+/// yards, and one big mall in the corner farthest from home.
+pub struct TownGenerator;
+
+impl TownGenerator {
+    fn is_street(x: i32, y: i32) -> bool {
+        (x - 1).rem_euclid(BLOCK_W + STREET) < STREET || (y - 1).rem_euclid(BLOCK_H + STREET) < STREET
+    }
+
+    fn blocks(map: &Map) -> Vec<Rect> {
+        let (w, h) = (map.width as i32, map.height as i32);
+        let mut blocks = vec![];
+        let mut by = 1 + STREET;
+        while by < h - 1 {
+            let mut bx = 1 + STREET;
+            while bx < w - 1 {
+                let block = Rect::new(bx, by, (bx + BLOCK_W - 1).min(w - 2), (by + BLOCK_H - 1).min(h - 2));
+                if block.x2 - block.x1 + 1 >= MIN_LOT_W && block.y2 - block.y1 + 1 >= 7 {
+                    blocks.push(block);
+                }
+                bx += BLOCK_W + STREET;
+            }
+            by += BLOCK_H + STREET;
+        }
+        blocks
+    }
+
+    /// Fills a rectangle (inclusive) with one tile type.
+    fn fill(map: &mut Map, r: &Rect, tile: TileType) {
+        for y in r.y1..=r.y2 {
+            for x in r.x1..=r.x2 {
+                if map.in_bounds(x, y) {
+                    let idx = map.xy_idx(x, y);
+                    map.tiles[idx] = tile;
+                }
+            }
+        }
+    }
+
+    fn set(map: &mut Map, x: i32, y: i32, tile: TileType) {
+        let idx = map.xy_idx(x, y);
+        map.tiles[idx] = tile;
+    }
+
+    /// Builds a walled house with floor `room` and a door in the wall facing
+    /// the street (`front_top` decides which way that is).
+    fn build_house(map: &mut Map, room: &Rect, front_top: bool, rng: &mut dyn RngCore) {
+        Self::fill(map, &Rect::new(room.x1 - 1, room.y1 - 1, room.x2 + 1, room.y2 + 1), TileType::Wall);
+        Self::fill(map, room, TileType::Floor);
+
+        let door_x = rng.gen_range(room.x1..=room.x2);
+        let door_y = if front_top { room.y1 - 1 } else { room.y2 + 1 };
+        Self::set(map, door_x, door_y, TileType::Door);
+
+        for side_x in [room.x1 - 1, room.x2 + 1] {
+            if rng.gen_bool(0.5) {
+                Self::set(map, side_x, rng.gen_range(room.y1..=room.y2), TileType::Window);
+            }
+        }
+    }
+
+    /// Stocks a house with loot and maybe a bed to rest in.
+    fn furnish(map: &mut Map, room: &Rect, rng: &mut dyn RngCore) {
+        for (tile, chance) in [(TileType::Resource, 0.6), (TileType::Weapon, 0.15), (TileType::Building, 0.3)] {
+            if rng.gen_bool(chance) {
+                let (x, y) = (rng.gen_range(room.x1..=room.x2), rng.gen_range(room.y1..=room.y2));
+                if map.tiles[map.xy_idx(x, y)] == TileType::Floor {
+                    Self::set(map, x, y, tile);
+                }
+            }
+        }
+    }
+
+    /// The mall fills a whole block: a parking lot around a big hall with a
+    /// door in the middle of every wall.
+    fn build_mall(map: &mut Map, block: &Rect) -> Rect {
+        let hall = Rect::new(block.x1 + 2, block.y1 + 2, block.x2 - 2, block.y2 - 2);
+        Self::fill(map, &Rect::new(hall.x1 - 1, hall.y1 - 1, hall.x2 + 1, hall.y2 + 1), TileType::Wall);
+        Self::fill(map, &hall, TileType::Floor);
+        let (cx, cy) = hall.center();
+        for (x, y) in [(cx, hall.y1 - 1), (cx, hall.y2 + 1), (hall.x1 - 1, cy), (hall.x2 + 1, cy)] {
+            Self::set(map, x, y, TileType::Door);
+        }
+        hall
+    }
+
+    fn build(map: &mut Map, rng: &mut dyn RngCore) {
+        let (w, h) = (map.width as i32, map.height as i32);
+
+        // Streets and open lots first; houses are walled in on top.
+        for y in 1..h - 1 {
+            for x in 1..w - 1 {
+                Self::set(map, x, y, TileType::Floor);
+            }
+        }
+
+        let blocks = Self::blocks(map);
+        let Some((mall_block, house_blocks)) = blocks.split_last() else {
+            return;
+        };
+
+        for block in house_blocks {
+            let lots = ((block.x2 - block.x1 + 1) / MIN_LOT_W).max(1);
+            let lot_w = (block.x2 - block.x1 + 1) / lots;
+            for i in 0..lots {
+                let lx1 = block.x1 + i * lot_w;
+                let lx2 = if i == lots - 1 { block.x2 } else { lx1 + lot_w - 1 };
+                let front_top = rng.gen_bool(0.5);
+                let depth = rng.gen_range(3..=5);
+                let (y1, y2) = if front_top {
+                    (block.y1 + 2, block.y1 + 1 + depth)
+                } else {
+                    (block.y2 - 1 - depth, block.y2 - 2)
+                };
+                let room = Rect::new(lx1 + 2, y1, lx2 - 2, y2);
+                Self::build_house(map, &room, front_top, rng);
+                Self::furnish(map, &room, rng);
+                map.rooms.push(room);
+                map.roofs.push(room);
+            }
+        }
+
+        // Overgrown yards around the houses.
+        for block in house_blocks {
+            for y in block.y1..=block.y2 {
+                for x in block.x1..=block.x2 {
+                    let inside = map.rooms.iter().any(|r| x >= r.x1 - 1 && x <= r.x2 + 1 && y >= r.y1 - 1 && y <= r.y2 + 1);
+                    if !inside && rng.gen_bool(0.3) {
+                        Self::set(map, x, y, TileType::Foliage);
+                    }
+                }
+            }
+        }
+
+        // Cars parked along the curb lanes; the middle lane stays clear.
+        for y in 1..h - 1 {
+            for x in 1..w - 1 {
+                if !Self::is_street(x, y) || map.tiles[map.xy_idx(x, y)] != TileType::Floor {
+                    continue;
+                }
+                let curb = [(0, -1), (1, 0), (0, 1), (-1, 0)]
+                    .iter()
+                    .any(|(dx, dy)| house_blocks.iter().any(|b| {
+                        let (nx, ny) = (x + dx, y + dy);
+                        nx >= b.x1 && nx <= b.x2 && ny >= b.y1 && ny <= b.y2
+                    }));
+                if curb && rng.gen_bool(0.08) {
+                    Self::set(map, x, y, TileType::Car);
+                }
+            }
+        }
+
+        let mall = Self::build_mall(map, mall_block);
+        map.rooms.push(mall);
+        map.roofs.push(mall);
+    }
+}
+
+impl MapGenerator for TownGenerator {
+    fn name(&self) -> &'static str {
+        "Suburb"
+    }
+
+    fn generate(&self, width: usize, height: usize, rng: &mut dyn RngCore) -> GeneratedMap {
+        let mut map = Map::new(width, height);
+        Self::build(&mut map, rng);
+        let placements = place_notes(&mut map, rng);
+        GeneratedMap::from_map(map, placements, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    fn town(seed: u64) -> Map {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        TownGenerator.generate(160, 47, &mut rng).map
+    }
+
+    #[test]
+    fn every_house_and_the_mall_can_be_walked_to() {
+        for seed in 0..5 {
+            let map = town(seed);
+            assert!(map.rooms.len() > 10);
+            let (sx, sy) = map.rooms[0].center();
+            let field = FlowField::from_target(&map, sx, sy, u32::MAX);
+            for room in &map.rooms {
+                let (x, y) = room.center();
+                assert!(field.distance(x, y).is_some(), "seed {}: room at ({}, {}) is cut off", seed, x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn town_has_notes_cars_and_a_mall_in_the_far_corner() {
+        let map = town(3);
+        let count = |t: TileType| map.tiles.iter().filter(|&&tile| tile == t).count();
+        assert_eq!(count(TileType::Note), 5);
+        assert!(count(TileType::Car) > 0);
+        let mall = map.rooms.last().unwrap();
+        assert!(mall.x1 > map.rooms[0].x2 && mall.y1 > map.rooms[0].y2);
+    }
+
+    #[test]
+    fn validation_rejects_a_walled_off_note() {
+        let mut map = Map::from_ascii(&["#########", "#..#.#..#", "#..###..#", "#########"]);
+        map.rooms = vec![Rect::new(1, 1, 2, 2), Rect::new(6, 1, 7, 2)];
+        let notes = vec![(4, 1), (1, 2), (2, 2), (6, 2), (7, 2)];
+        for &(x, y) in &notes {
+            let idx = map.xy_idx(x, y);
+            map.tiles[idx] = TileType::Note;
+        }
+        let upper = Map::new(map.width, map.height);
+        let generated = GeneratedMap { map, upper, upper_zombies: vec![], spawn: (1, 1), mall: Rect::new(6, 1, 7, 1), notes, placements: vec![] };
+        let err = generated.validate().unwrap_err();
+        assert!(err.contains("unreachable"), "{}", err);
+    }
+
+    #[test]
+    fn validation_wants_stairs_from_the_mall_to_a_helipad() {
+        let mut rng = ChaCha8Rng::seed_from_u64(4);
+        let mut generated = generate_valid(&TownGenerator, 160, 47, &mut rng).unwrap();
+        let mall = generated.mall;
+        let on_roof = |generated: &GeneratedMap, tile: TileType| {
+            (mall.y1..=mall.y2)
+                .flat_map(|y| (mall.x1..=mall.x2).map(move |x| (x, y)))
+                .find(|&(x, y)| generated.upper.tiles[generated.upper.xy_idx(x, y)] == tile)
+                .unwrap()
+        };
+
+        // Fence the helipad off from the top of the stairs.
+        let (hx, hy) = on_roof(&generated, TileType::Helipad);
+        for (x, y) in [(hx - 1, hy), (hx + 1, hy), (hx, hy - 1), (hx, hy + 1)] {
+            let idx = generated.upper.xy_idx(x, y);
+            generated.upper.tiles[idx] = TileType::Wall;
+        }
+        let err = generated.validate().unwrap_err();
+        assert!(err.contains("helipad"), "{}", err);
+
+        let (sx, sy) = on_roof(&generated, TileType::StairsDown);
+        let idx = generated.map.xy_idx(sx, sy);
+        generated.map.tiles[idx] = TileType::Floor;
+        let err = generated.validate().unwrap_err();
+        assert!(err.contains("stairs"), "{}", err);
+    }
+
+    #[test]
+    fn both_generators_produce_valid_maps() {
+        for kind in [MapKind::Bsp, MapKind::Town] {
+            for seed in 0..10 {
+                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                let generated = generate_valid(kind.generator(), 160, 47, &mut rng).unwrap();
+                assert_eq!(generated.notes.len(), NOTE_COUNT);
+                assert_eq!(generated.map.tiles[generated.map.xy_idx(generated.spawn.0, generated.spawn.1)], TileType::Floor);
+            }
+        }
+    }
+
+    #[test]
+    fn notes_spread_outward_through_distinct_rooms() {
+        for kind in [MapKind::Bsp, MapKind::Town] {
+            for seed in 0..10 {
+                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                let generated = generate_valid(kind.generator(), 160, 47, &mut rng).unwrap();
+                let placements = &generated.placements;
+                assert_eq!(placements.len(), NOTE_COUNT);
+
+                let last = generated.map.rooms.len() - 1;
+                let mut rooms: Vec<usize> = placements.iter().map(|p| p.room).collect();
+                assert!(rooms.iter().all(|&r| r != 0 && r != last), "{:?} seed {}: note in the spawn room or mall", kind, seed);
+                rooms.dedup();
+                assert_eq!(rooms.len(), NOTE_COUNT, "{:?} seed {}: two notes share a room", kind, seed);
+
+                let field = FlowField::from_target(&generated.map, generated.spawn.0, generated.spawn.1, u32::MAX);
+                let room_distance = |r: usize| {
+                    let room = generated.map.rooms[r];
+                    (room.y1..=room.y2)
+                        .flat_map(|y| (room.x1..=room.x2).map(move |x| (x, y)))
+                        .filter_map(|(x, y)| field.distance(x, y))
+                        .min()
+                        .unwrap()
+                };
+                assert!(placements.windows(2).all(|w| room_distance(w[0].room) <= room_distance(w[1].room)));
+            }
+        }
+    }
+
+    #[test]
+    fn map_kind_round_trips_through_its_cli_name() {
+        for kind in [MapKind::Bsp, MapKind::Town] {
+            assert_eq!(MapKind::from_arg(kind.arg()), Some(kind));
+        }
+        assert_eq!(MapKind::from_arg("castle"), None);
+    }
+}
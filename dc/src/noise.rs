@@ -0,0 +1,149 @@
+// --- Noise ---
+//
+// Player actions emit noise events that last for a single turn. Zombies that
+// are not already chasing the player turn toward the loudest noise in earshot,
+// and the map draws each event's reach so the player can judge the risk.
+
+use crate::weather::Weather;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NoiseKind {
+    Gunshot,
+    ShotgunBlast,
+    KnifeSwing,
+    BluntImpact,
+    CarSearch,
+    Foliage,
+    Thrown,
+    Firebomb,
+    Hammering,
+    BarricadeBreak,
+}
+
+/// The furthest any noise carries.
+pub const MAX_RADIUS: i32 = NoiseKind::ShotgunBlast.radius();
+
+impl NoiseKind {
+    /// How far the sound carries, in tiles.
+    pub const fn radius(self) -> i32 {
+        match self {
+            NoiseKind::Gunshot => 15,
+            NoiseKind::ShotgunBlast => 20,
+            NoiseKind::KnifeSwing => 2,
+            NoiseKind::BluntImpact => 3,
+            NoiseKind::CarSearch => 6,
+            NoiseKind::Foliage => 2,
+            NoiseKind::Thrown => 5,
+            NoiseKind::Firebomb => 10,
+            NoiseKind::Hammering => 8,
+            NoiseKind::BarricadeBreak => 9,
+        }
+    }
+
+    /// How strongly the sound pulls zombies; louder wins when several overlap.
+    pub fn loudness(self) -> u8 {
+        match self {
+            NoiseKind::Gunshot => 10,
+            NoiseKind::ShotgunBlast => 12,
+            NoiseKind::KnifeSwing => 2,
+            NoiseKind::BluntImpact => 3,
+            NoiseKind::CarSearch => 5,
+            NoiseKind::Foliage => 1,
+            NoiseKind::Thrown => 4,
+            NoiseKind::Firebomb => 8,
+            NoiseKind::Hammering => 4,
+            NoiseKind::BarricadeBreak => 7,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            NoiseKind::Gunshot => "BANG",
+            NoiseKind::ShotgunBlast => "BOOM",
+            NoiseKind::KnifeSwing => "swish",
+            NoiseKind::BluntImpact => "thwack",
+            NoiseKind::CarSearch => "clunk",
+            NoiseKind::Foliage => "rustle",
+            NoiseKind::Thrown => "clatter",
+            NoiseKind::Firebomb => "WHOOMPH",
+            NoiseKind::Hammering => "bang-bang",
+            NoiseKind::BarricadeBreak => "CRASH",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct NoiseEvent {
+    pub x: i32,
+    pub y: i32,
+    pub kind: NoiseKind,
+    pub radius: i32,
+    pub loudness: u8,
+}
+
+impl NoiseEvent {
+    pub fn new(x: i32, y: i32, kind: NoiseKind) -> Self {
+        NoiseEvent { x, y, kind, radius: kind.radius(), loudness: kind.loudness() }
+    }
+
+    /// The same noise as it carries through `weather`.
+    pub fn through(mut self, weather: Weather) -> Self {
+        self.radius = weather.noise_radius(self.radius);
+        self
+    }
+
+    pub fn reaches(&self, x: i32, y: i32) -> bool {
+        let (dx, dy) = (x - self.x, y - self.y);
+        dx * dx + dy * dy <= self.radius * self.radius
+    }
+
+    /// True for tiles on the outer edge of the noise, used to draw its ring.
+    pub fn on_edge(&self, x: i32, y: i32) -> bool {
+        let (dx, dy) = (x - self.x, y - self.y);
+        let dist = ((dx * dx + dy * dy) as f32).sqrt();
+        (dist - self.radius as f32).abs() < 0.5
+    }
+}
+
+/// The loudest noise that reaches `(x, y)`, if any.
+pub fn loudest_heard(noises: &[NoiseEvent], x: i32, y: i32) -> Option<&NoiseEvent> {
+    noises.iter().filter(|n| n.reaches(x, y)).max_by_key(|n| n.loudness)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn noise_reaches_a_circle_of_its_radius() {
+        let shot = NoiseEvent::new(10, 10, NoiseKind::Gunshot);
+        assert!(shot.reaches(25, 10));
+        assert!(!shot.reaches(26, 10));
+        assert!(shot.reaches(19, 22), "9-12-15 sits on the edge");
+        assert!(!shot.reaches(21, 21));
+    }
+
+    #[test]
+    fn the_loudest_noise_in_earshot_wins() {
+        let noises = [
+            NoiseEvent::new(0, 0, NoiseKind::Gunshot),
+            NoiseEvent::new(4, 0, NoiseKind::CarSearch),
+            NoiseEvent::new(6, 0, NoiseKind::KnifeSwing),
+        ];
+        assert_eq!(loudest_heard(&noises, 5, 0).map(|n| n.kind), Some(NoiseKind::Gunshot));
+        assert_eq!(loudest_heard(&noises, 22, 0).map(|n| n.kind), None);
+        assert_eq!(loudest_heard(&noises[1..], 7, 0).map(|n| n.kind), Some(NoiseKind::CarSearch));
+        assert!(loudest_heard(&[], 0, 0).is_none());
+    }
+
+    #[test]
+    fn rain_and_storms_muffle_noise() {
+        let shot = NoiseEvent::new(0, 0, NoiseKind::Gunshot);
+        assert_eq!(shot.through(Weather::Clear).radius, 15);
+        assert_eq!(shot.through(Weather::Rain).radius, 11);
+        assert_eq!(shot.through(Weather::Storm).radius, 7);
+        assert!(!shot.through(Weather::Storm).reaches(10, 0));
+        assert_eq!(NoiseEvent::new(0, 0, NoiseKind::Foliage).through(Weather::Storm).radius, 1);
+    }
+}
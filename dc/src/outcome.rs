@@ -0,0 +1,89 @@
+// --- Outcome ---
+//
+// How a run ended. The first thing to end it wins: a bite landing on the turn
+// the player reaches the helipad does not undo the rescue, and the needs
+// ticking down afterwards do not rewrite a death by bite.
+
+use crate::ZombieKind;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CauseOfDeath {
+    Starvation,
+    Dehydration,
+    Exhaustion,
+    Bite(ZombieKind),
+}
+
+impl CauseOfDeath {
+    pub fn label(self) -> String {
+        match self {
+            CauseOfDeath::Starvation => "Starvation".to_string(),
+            CauseOfDeath::Dehydration => "Dehydration".to_string(),
+            CauseOfDeath::Exhaustion => "Exhaustion".to_string(),
+            CauseOfDeath::Bite(kind) => format!("Bitten by a {}", kind.name()),
+        }
+    }
+
+    pub fn describe(self) -> String {
+        match self {
+            CauseOfDeath::Starvation => "You starved to death.".to_string(),
+            CauseOfDeath::Dehydration => "You died of thirst.".to_string(),
+            CauseOfDeath::Exhaustion => "You collapsed from exhaustion and never got up.".to_string(),
+            CauseOfDeath::Bite(kind) => format!("A {} bit you one time too many.", kind.name()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Outcome {
+    /// Held the mall roof until the chopper landed.
+    Victory,
+    Death(CauseOfDeath),
+    /// Walked away from the run without saving.
+    Quit,
+}
+
+impl Outcome {
+    /// One-line fate for the run summary.
+    pub fn fate(self) -> String {
+        match self {
+            Outcome::Victory => "Rescued by chopper".to_string(),
+            Outcome::Death(cause) => cause.label(),
+            Outcome::Quit => "Walked away".to_string(),
+        }
+    }
+
+    pub fn headline(self) -> String {
+        match self {
+            Outcome::Victory => "YOU ESCAPED! The chopper lifts off from the mall roof.".to_string(),
+            Outcome::Death(cause) => format!("GAME OVER. {}", cause.describe()),
+            Outcome::Quit => "RUN ABANDONED. The city keeps its secrets.".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GameState;
+
+    #[test]
+    fn the_first_blow_decides_the_outcome() {
+        let mut state = GameState::new(0);
+        state.health = 3;
+        state.hurt(2, CauseOfDeath::Starvation);
+        assert_eq!(state.outcome, None);
+        state.hurt(4, CauseOfDeath::Bite(ZombieKind::Brute));
+        state.hurt(1, CauseOfDeath::Dehydration);
+        assert_eq!(state.outcome, Some(Outcome::Death(CauseOfDeath::Bite(ZombieKind::Brute))));
+    }
+
+    #[test]
+    fn a_bite_after_the_rescue_does_not_undo_it() {
+        let mut state = GameState::new(0);
+        state.outcome = Some(Outcome::Victory);
+        state.hurt(200, CauseOfDeath::Bite(ZombieKind::Shambler));
+        assert_eq!(state.outcome, Some(Outcome::Victory));
+    }
+}
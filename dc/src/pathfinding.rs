@@ -0,0 +1,120 @@
+// --- Pathfinding ---
+//
+// A Dijkstra flow field is built once per turn outward from a single target
+// (normally the player). Every zombie then walks downhill on the same field, so
+// the cost is one bounded flood fill per turn instead of one search per zombie.
+
+use crate::Map;
+use std::collections::VecDeque;
+
+const NEIGHBOURS: [(i32, i32); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+
+pub struct FlowField {
+    width: usize,
+    height: usize,
+    dist: Vec<u32>,
+}
+
+impl FlowField {
+    /// Floods outward from `(tx, ty)` over passable tiles, stopping once the
+    /// step count reaches `max_cost`. Tiles past the cap stay unreachable, which
+    /// bounds the work no matter how large the map is. Barricades count as
+    /// passable so zombies walk up to them and bash them down.
+    pub fn from_target(map: &Map, tx: i32, ty: i32, max_cost: u32) -> Self {
+        let mut field = FlowField {
+            width: map.width,
+            height: map.height,
+            dist: vec![u32::MAX; map.width * map.height],
+        };
+        if !map.in_bounds(tx, ty) {
+            return field;
+        }
+
+        let mut frontier = VecDeque::new();
+        field.dist[map.xy_idx(tx, ty)] = 0;
+        frontier.push_back((tx, ty));
+
+        while let Some((x, y)) = frontier.pop_front() {
+            let here = field.dist[map.xy_idx(x, y)];
+            if here >= max_cost {
+                continue;
+            }
+            for (dx, dy) in NEIGHBOURS {
+                let (nx, ny) = (x + dx, y + dy);
+                if !map.is_passable(nx, ny) {
+                    continue;
+                }
+                let idx = map.xy_idx(nx, ny);
+                if field.dist[idx] == u32::MAX {
+                    field.dist[idx] = here + 1;
+                    frontier.push_back((nx, ny));
+                }
+            }
+        }
+        field
+    }
+
+    /// Steps from `(x, y)` to the target, or `None` if it is out of reach.
+    pub fn distance(&self, x: i32, y: i32) -> Option<u32> {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return None;
+        }
+        let d = self.dist[y as usize * self.width + x as usize];
+        (d != u32::MAX).then_some(d)
+    }
+
+    /// The neighbouring tile that gets closest to the target, skipping any
+    /// tile for which `blocked` returns true (e.g. one held by another zombie).
+    pub fn next_step(&self, x: i32, y: i32, blocked: impl Fn(i32, i32) -> bool) -> Option<(i32, i32)> {
+        let here = self.distance(x, y)?;
+        NEIGHBOURS
+            .iter()
+            .map(|(dx, dy)| (x + dx, y + dy))
+            .filter(|&(nx, ny)| !blocked(nx, ny))
+            .filter_map(|(nx, ny)| self.distance(nx, ny).map(|d| (d, (nx, ny))))
+            .filter(|&(d, _)| d < here)
+            .min_by_key(|&(d, _)| d)
+            .map(|(_, pos)| pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes_around_a_wall_corner() {
+        let map = Map::from_ascii(&[
+            "#######",
+            "#.....#",
+            "#.###.#",
+            "#.#...#",
+            "#######",
+        ]);
+        let field = FlowField::from_target(&map, 3, 3, 50);
+        // Straight up is a wall, so the zombie at (3, 1) has to go round.
+        assert_eq!(field.distance(3, 1), Some(6));
+        let step = field.next_step(3, 1, |_, _| false).unwrap();
+        assert_eq!(step, (4, 1));
+    }
+
+    #[test]
+    fn cost_cap_limits_the_flood() {
+        let map = Map::from_ascii(&["..........."]);
+        let field = FlowField::from_target(&map, 0, 0, 4);
+        assert_eq!(field.distance(4, 0), Some(4));
+        assert_eq!(field.distance(5, 0), None);
+    }
+
+    #[test]
+    fn blocked_tiles_are_skipped() {
+        let map = Map::from_ascii(&[
+            "...",
+            "...",
+            "...",
+        ]);
+        let field = FlowField::from_target(&map, 1, 2, 10);
+        let step = field.next_step(1, 0, |x, y| (x, y) == (1, 1));
+        assert_eq!(step, None, "the only closer tile is occupied");
+    }
+}
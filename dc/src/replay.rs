@@ -0,0 +1,285 @@
+// --- Replays ---
+//
+// A run is fully determined by its seed, its map kind and the keys pressed
+// while it was live, so that is all a replay stores. Recording captures the
+// keys as the main loop handles them, appending each to the replay file as it
+// is pressed; playback starts a fresh run from the same seed and feeds the
+// keys back one at a time, paced by the viewer.
+//
+// The file is JSON lines: a header with the format version, seed and map
+// kind, then one key per line.
+
+use crate::mapgen::MapKind;
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Result,
+};
+use crossterm::event::KeyCode;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Bump whenever the replay file changes shape, or the game changes in a way that
+/// makes old key streams play out differently.
+pub const REPLAY_VERSION: u32 = 3;
+/// Playback speeds, as multiples of `BASE_DELAY` per key.
+const SPEEDS: [f32; 5] = [0.5, 1.0, 2.0, 4.0, 16.0];
+const BASE_DELAY: Duration = Duration::from_millis(400);
+
+/// The keys the game responds to, in a form that survives serialization.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReplayKey {
+    Char(char),
+    Enter,
+    Esc,
+    Up,
+    Down,
+    Left,
+    Right,
+    Tab,
+    BackTab,
+    Backspace,
+    F(u8),
+}
+
+impl ReplayKey {
+    pub fn from_code(code: KeyCode) -> Option<Self> {
+        Some(match code {
+            KeyCode::Char(c) => ReplayKey::Char(c),
+            KeyCode::Enter => ReplayKey::Enter,
+            KeyCode::Esc => ReplayKey::Esc,
+            KeyCode::Up => ReplayKey::Up,
+            KeyCode::Down => ReplayKey::Down,
+            KeyCode::Left => ReplayKey::Left,
+            KeyCode::Right => ReplayKey::Right,
+            KeyCode::Tab => ReplayKey::Tab,
+            KeyCode::BackTab => ReplayKey::BackTab,
+            KeyCode::Backspace => ReplayKey::Backspace,
+            KeyCode::F(n) => ReplayKey::F(n),
+            _ => return None,
+        })
+    }
+
+    pub fn code(self) -> KeyCode {
+        match self {
+            ReplayKey::Char(c) => KeyCode::Char(c),
+            ReplayKey::Enter => KeyCode::Enter,
+            ReplayKey::Esc => KeyCode::Esc,
+            ReplayKey::Up => KeyCode::Up,
+            ReplayKey::Down => KeyCode::Down,
+            ReplayKey::Left => KeyCode::Left,
+            ReplayKey::Right => KeyCode::Right,
+            ReplayKey::Tab => KeyCode::Tab,
+            ReplayKey::BackTab => KeyCode::BackTab,
+            ReplayKey::Backspace => KeyCode::Backspace,
+            ReplayKey::F(n) => KeyCode::F(n),
+        }
+    }
+}
+
+/// The first line of a replay file.
+#[derive(Serialize, Deserialize)]
+struct Header {
+    version: u32,
+    seed: u64,
+    map_kind: MapKind,
+}
+
+#[derive(Clone, Debug)]
+pub struct Replay {
+    pub seed: u64,
+    pub map_kind: MapKind,
+    pub keys: Vec<ReplayKey>,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).wrap_err_with(|| format!("could not read {}", path.display()))?;
+        let mut lines = text.lines();
+        let value: serde_json::Value =
+            serde_json::from_str(lines.next().unwrap_or_default()).wrap_err("replay file is corrupt")?;
+        match value.get("version").and_then(|v| v.as_u64()) {
+            Some(v) if v == REPLAY_VERSION as u64 => {}
+            Some(v) => return Err(eyre!("replay is version {}, this build plays version {}", v, REPLAY_VERSION)),
+            None => return Err(eyre!("replay has no format version")),
+        }
+        let header: Header = serde_json::from_value(value).wrap_err("replay does not match the current format")?;
+
+        let lines: Vec<&str> = lines.collect();
+        let mut keys = Vec::with_capacity(lines.len());
+        for (i, line) in lines.iter().enumerate() {
+            match serde_json::from_str(line) {
+                Ok(key) => keys.push(key),
+                // A run killed mid-write leaves half a key on the last line.
+                Err(_) if i + 1 == lines.len() => break,
+                Err(err) => return Err(eyre!("replay is corrupt at line {}: {}", i + 2, err)),
+            }
+        }
+        Ok(Replay { seed: header.seed, map_kind: header.map_kind, keys })
+    }
+}
+
+/// Records a live run. The header goes to disk when the run starts and every
+/// key is appended as it is handled, unbuffered, so a save-and-quit, a panic
+/// or a killed process loses nothing already played.
+pub struct Recorder {
+    file: File,
+    pub path: PathBuf,
+}
+
+impl Recorder {
+    /// Starts a replay file in `dir`, named from the seed and the start time.
+    pub fn start(dir: &Path, seed: u64, map_kind: MapKind, started_at: u64) -> Result<Self> {
+        fs::create_dir_all(dir).wrap_err_with(|| format!("could not create {}", dir.display()))?;
+        let path = dir.join(format!("run-{}-{}.jsonl", seed, started_at));
+        let file = File::create(&path).wrap_err_with(|| format!("could not create {}", path.display()))?;
+        let mut recorder = Recorder { file, path };
+        let header = Header { version: REPLAY_VERSION, seed, map_kind };
+        recorder.append(&serde_json::to_string(&header)?)?;
+        Ok(recorder)
+    }
+
+    /// Appends a key; keys the game ignores are skipped.
+    pub fn record(&mut self, code: KeyCode) -> Result<()> {
+        match ReplayKey::from_code(code) {
+            Some(key) => self.append(&serde_json::to_string(&key)?),
+            None => Ok(()),
+        }
+    }
+
+    fn append(&mut self, line: &str) -> Result<()> {
+        self.file
+            .write_all(format!("{}\n", line).as_bytes())
+            .wrap_err_with(|| format!("could not write {}", self.path.display()))
+    }
+}
+
+/// Feeds a recorded run back to the game, one key per tick of the chosen
+/// speed. Paused playback only moves when stepped.
+pub struct Playback {
+    pub replay: Replay,
+    /// Index of the next key to play.
+    pub pos: usize,
+    pub paused: bool,
+    speed: usize,
+    step: bool,
+    last: Instant,
+}
+
+impl Playback {
+    pub fn new(replay: Replay, now: Instant) -> Self {
+        Playback { replay, pos: 0, paused: false, speed: 1, step: false, last: now }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.pos >= self.replay.keys.len()
+    }
+
+    pub fn speed(&self) -> f32 {
+        SPEEDS[self.speed]
+    }
+
+    fn delay(&self) -> Duration {
+        Duration::from_millis((BASE_DELAY.as_millis() as f32 / self.speed()) as u64)
+    }
+
+    /// Handles a viewer key (space pauses, `.` steps, `+`/`-` change speed).
+    /// Returns false if the key is not a playback control.
+    pub fn control(&mut self, code: KeyCode) -> bool {
+        match code {
+            KeyCode::Char(' ') => self.paused = !self.paused,
+            KeyCode::Char('.') => {
+                self.paused = true;
+                self.step = true;
+            }
+            KeyCode::Char('+') | KeyCode::Char('=') => self.speed = (self.speed + 1).min(SPEEDS.len() - 1),
+            KeyCode::Char('-') => self.speed = self.speed.saturating_sub(1),
+            _ => return false,
+        }
+        true
+    }
+
+    /// How long the main loop may wait for viewer input before the next key
+    /// is due.
+    pub fn wait(&self, now: Instant) -> Duration {
+        if self.paused || self.is_finished() {
+            return Duration::from_millis(150);
+        }
+        self.delay().saturating_sub(now.duration_since(self.last))
+    }
+
+    /// The next recorded key, if it is due.
+    pub fn next_key(&mut self, now: Instant) -> Option<KeyCode> {
+        let due = if self.paused { self.step } else { now.duration_since(self.last) >= self.delay() };
+        if !due || self.is_finished() {
+            return None;
+        }
+        self.step = false;
+        self.last = now;
+        self.pos += 1;
+        Some(self.replay.keys[self.pos - 1].code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replay(keys: &str) -> Replay {
+        Replay { seed: 99, map_kind: MapKind::Town, keys: keys.chars().map(ReplayKey::Char).collect() }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("dc-replay-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn replays_are_on_disk_while_the_run_is_still_going() {
+        let dir = temp_dir("live");
+        let mut recorder = Recorder::start(&dir, 99, MapKind::Town, 1234).unwrap();
+        for c in "wasd".chars() {
+            recorder.record(KeyCode::Char(c)).unwrap();
+        }
+        recorder.record(KeyCode::F(3)).unwrap();
+        recorder.record(KeyCode::Home).unwrap();
+
+        // Read back without closing the recorder, as after a kill.
+        let loaded = Replay::load(&recorder.path).unwrap();
+        assert_eq!(loaded.seed, 99);
+        assert_eq!(loaded.map_kind, MapKind::Town);
+        assert_eq!(loaded.keys.len(), 5, "keys the game ignores are not recorded");
+        assert_eq!(loaded.keys[4].code(), KeyCode::F(3));
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn a_half_written_last_key_is_dropped_but_other_damage_is_reported() {
+        let dir = temp_dir("torn");
+        let mut recorder = Recorder::start(&dir, 5, MapKind::Bsp, 1).unwrap();
+        recorder.record(KeyCode::Char('w')).unwrap();
+        recorder.append("{\"Ch").unwrap();
+        assert_eq!(Replay::load(&recorder.path).unwrap().keys, vec![ReplayKey::Char('w')]);
+
+        recorder.record(KeyCode::Char('s')).unwrap();
+        assert!(Replay::load(&recorder.path).is_err());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn playback_keeps_pace_and_steps_while_paused() {
+        let start = Instant::now();
+        let mut playback = Playback::new(replay("ab"), start);
+        assert_eq!(playback.next_key(start), None, "nothing is due straight away");
+        let later = start + BASE_DELAY;
+        assert_eq!(playback.next_key(later), Some(KeyCode::Char('a')));
+
+        playback.control(KeyCode::Char(' '));
+        assert_eq!(playback.next_key(later + BASE_DELAY * 10), None);
+        playback.control(KeyCode::Char('.'));
+        assert_eq!(playback.next_key(later), Some(KeyCode::Char('b')));
+        assert!(playback.is_finished());
+        assert!(!playback.control(KeyCode::Char('x')));
+    }
+}
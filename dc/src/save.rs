@@ -0,0 +1,67 @@
+// --- Save / Load ---
+//
+// A save is a single JSON file holding every level of the world, the game
+// state and the RNG stream, tagged with a format version. Loading checks the
+// version before touching anything else so an old save fails with a readable
+// error.
+
+use crate::world::World;
+use crate::GameState;
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Result,
+};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Bump whenever `World`, `Map`, `GameState` or anything they contain changes shape.
+pub const SAVE_VERSION: u32 = 18;
+pub const SAVE_PATH: &str = "zomboid_save.json";
+
+#[derive(Serialize)]
+struct SaveRef<'a> {
+    version: u32,
+    world: &'a World,
+    state: &'a GameState,
+    rng: &'a ChaCha8Rng,
+}
+
+#[derive(Deserialize)]
+struct SaveData {
+    world: World,
+    state: GameState,
+    rng: ChaCha8Rng,
+}
+
+pub fn save_exists() -> bool {
+    Path::new(SAVE_PATH).exists()
+}
+
+pub fn save_game(world: &World, state: &GameState, rng: &ChaCha8Rng) -> Result<()> {
+    let data = SaveRef { version: SAVE_VERSION, world, state, rng };
+    let json = serde_json::to_string(&data)?;
+    fs::write(SAVE_PATH, json).wrap_err_with(|| format!("could not write {}", SAVE_PATH))?;
+    Ok(())
+}
+
+pub fn load_game() -> Result<(World, GameState, ChaCha8Rng)> {
+    let text = fs::read_to_string(SAVE_PATH).wrap_err_with(|| format!("could not read {}", SAVE_PATH))?;
+    let value: serde_json::Value = serde_json::from_str(&text).wrap_err("save file is corrupt")?;
+
+    let version = value.get("version").and_then(|v| v.as_u64());
+    match version {
+        Some(v) if v == SAVE_VERSION as u64 => {}
+        Some(v) => return Err(eyre!("save file is version {}, this build reads version {}", v, SAVE_VERSION)),
+        None => return Err(eyre!("save file has no format version")),
+    }
+
+    let data: SaveData = serde_json::from_value(value).wrap_err("save file does not match the current format")?;
+    Ok((data.world, data.state, data.rng))
+}
+
+/// Runs end for good on death or victory, so their save is discarded.
+pub fn delete_save() {
+    let _ = fs::remove_file(SAVE_PATH);
+}
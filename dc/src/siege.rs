@@ -0,0 +1,134 @@
+// --- Siege ---
+//
+// The finale. With all five notes in, stepping onto the helipad fires the
+// flare and the chopper sets out. It only closes in while the player holds
+// the mall roof, and every few turns another, bigger wave of the dead comes
+// up the mall stairs to drag them off it.
+
+use crate::Rect;
+use serde::{Deserialize, Serialize};
+
+/// Turns the player must hold the roof before the chopper lands.
+pub const CHOPPER_TURNS: u32 = 40;
+/// Turns between waves.
+const WAVE_INTERVAL: u32 = 6;
+/// The siege ignores the usual zombie cap, up to a point.
+pub const MAX_SIEGE_ZOMBIES: usize = 40;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Siege {
+    /// Turns of holding the roof still needed.
+    pub turns_left: u32,
+    /// Waves sent so far.
+    pub wave: u32,
+    /// The mall roof, on the upper level.
+    pub roof: Rect,
+    next_wave: u32,
+}
+
+impl Siege {
+    pub fn new(roof: Rect) -> Self {
+        // The first wave is already on the stairs when the flare goes up.
+        Siege { turns_left: CHOPPER_TURNS, wave: 0, roof, next_wave: 2 }
+    }
+
+    /// Zombies in the given wave; each one brings two more than the last.
+    pub fn wave_size(wave: u32) -> usize {
+        2 * wave as usize
+    }
+
+    /// Advances one turn. The siege stands still while the player is off the
+    /// roof; otherwise the chopper gets closer and, every `WAVE_INTERVAL`
+    /// turns, returns the size of the wave arriving now.
+    pub fn tick(&mut self, holding_roof: bool) -> Option<usize> {
+        if !holding_roof {
+            return None;
+        }
+        self.turns_left = self.turns_left.saturating_sub(1);
+        self.next_wave = self.next_wave.saturating_sub(1);
+        if self.next_wave > 0 {
+            return None;
+        }
+        self.next_wave = WAVE_INTERVAL;
+        self.wave += 1;
+        Some(Self::wave_size(self.wave))
+    }
+
+    pub fn chopper_arrived(&self) -> bool {
+        self.turns_left == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outcome::Outcome;
+    use crate::world::UPPER_LEVEL;
+    use crate::{advance_siege, GameState, Map, Zombie, ZombieKind};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn waves_grow_and_the_chopper_only_comes_while_the_roof_is_held() {
+        let mut siege = Siege::new(Rect::new(0, 0, 9, 9));
+        let mut waves = vec![];
+        for _ in 0..20 {
+            waves.extend(siege.tick(true));
+        }
+        assert_eq!(waves, vec![2, 4, 6, 8]);
+        assert_eq!(siege.turns_left, CHOPPER_TURNS - 20);
+
+        for _ in 0..50 {
+            assert_eq!(siege.tick(false), None);
+        }
+        assert_eq!(siege.turns_left, CHOPPER_TURNS - 20);
+
+        while !siege.chopper_arrived() {
+            siege.tick(true);
+        }
+        assert_eq!(siege.wave, 7);
+    }
+
+    fn roof() -> Map {
+        Map::from_ascii(&["############", "#..........#", "#.........>#", "#..........#", "############"])
+    }
+
+    #[test]
+    fn holding_the_roof_brings_waves_then_the_chopper() {
+        let roof = roof();
+        let mut state = GameState::new(0);
+        state.level = UPPER_LEVEL;
+        (state.player_x, state.player_y) = (1, 2);
+        state.siege = Some(Siege::new(Rect::new(1, 1, 10, 3)));
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+
+        advance_siege(&roof, &mut state, &mut rng);
+        advance_siege(&roof, &mut state, &mut rng);
+        assert_eq!(state.zombies.len(), 2);
+        assert!(state.zombies.iter().all(|z| z.is_alerted() && (z.x - 10).abs() + (z.y - 2).abs() <= 1), "they come up the stairs");
+
+        state.zombies.clear();
+        for _ in 2..CHOPPER_TURNS {
+            advance_siege(&roof, &mut state, &mut rng);
+        }
+        assert_eq!(state.outcome, Some(Outcome::Victory));
+    }
+
+    #[test]
+    fn a_full_roof_sends_no_wave_and_says_nothing() {
+        let roof = roof();
+        let mut state = GameState::new(0);
+        state.level = UPPER_LEVEL;
+        (state.player_x, state.player_y) = (1, 2);
+        state.siege = Some(Siege::new(Rect::new(1, 1, 10, 3)));
+        state.zombies = (0..MAX_SIEGE_ZOMBIES).map(|_| Zombie::new(0, 0, ZombieKind::Shambler)).collect();
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+
+        let log_before = state.message_log.len();
+        advance_siege(&roof, &mut state, &mut rng);
+        advance_siege(&roof, &mut state, &mut rng);
+        assert_eq!(state.siege.unwrap().wave, 1);
+        assert_eq!(state.zombies.len(), MAX_SIEGE_ZOMBIES);
+        assert_eq!(state.message_log.len(), log_before);
+    }
+}
@@ -0,0 +1,86 @@
+// --- Run Statistics ---
+//
+// Tallies kept while playing, fed by the combat, movement and scavenging code
+// as things happen, and laid out as a table on the game-over screen.
+
+use crate::outcome::Outcome;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RunStats {
+    /// Zombies put down, by what did it.
+    pub kills: BTreeMap<String, u32>,
+    pub shots_fired: u32,
+    pub shots_hit: u32,
+    /// Successful searches, by what was searched.
+    pub scavenged: BTreeMap<String, u32>,
+    /// Tiles walked.
+    pub distance: u32,
+}
+
+impl RunStats {
+    pub fn record_kill(&mut self, by: &str, count: u32) {
+        if count > 0 {
+            *self.kills.entry(by.to_string()).or_default() += count;
+        }
+    }
+
+    pub fn record_shot(&mut self, hit: bool) {
+        self.shots_fired += 1;
+        if hit {
+            self.shots_hit += 1;
+        }
+    }
+
+    pub fn record_scavenge(&mut self, source: &str) {
+        *self.scavenged.entry(source.to_string()).or_default() += 1;
+    }
+
+    pub fn record_step(&mut self) {
+        self.distance += 1;
+    }
+
+    pub fn total_kills(&self) -> u32 {
+        self.kills.values().sum()
+    }
+
+    /// Fraction of shots that landed, or `None` before the first shot.
+    pub fn hit_rate(&self) -> Option<f32> {
+        (self.shots_fired > 0).then(|| self.shots_hit as f32 / self.shots_fired as f32)
+    }
+
+    /// 10 per kill, 50 per note, 1 per 10 turns survived, and 500 for
+    /// getting out alive.
+    pub fn score(&self, turns: u32, notes: u32, outcome: Outcome) -> u32 {
+        let escape = if outcome == Outcome::Victory { 500 } else { 0 };
+        self.total_kills() * 10 + notes * 50 + turns / 10 + escape
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outcome::CauseOfDeath;
+
+    #[test]
+    fn tallies_kills_shots_and_searches() {
+        let mut stats = RunStats::default();
+        assert_eq!(stats.hit_rate(), None);
+        stats.record_kill("Pistol", 1);
+        stats.record_kill("Molotov", 3);
+        stats.record_kill("Pistol", 1);
+        stats.record_kill("Molotov", 0);
+        stats.record_shot(true);
+        stats.record_shot(false);
+        stats.record_scavenge("Cars");
+        stats.record_scavenge("Cars");
+
+        assert_eq!(stats.kills["Pistol"], 2);
+        assert_eq!(stats.total_kills(), 5);
+        assert_eq!(stats.hit_rate(), Some(0.5));
+        assert_eq!(stats.scavenged["Cars"], 2);
+        assert_eq!(stats.score(120, 5, Outcome::Victory), 50 + 250 + 12 + 500);
+        assert_eq!(stats.score(120, 5, Outcome::Death(CauseOfDeath::Starvation)), 50 + 250 + 12);
+    }
+}
@@ -0,0 +1,88 @@
+// --- Ranged Targeting ---
+//
+// Firearms pick targets from the zombies the player can currently see. A
+// target is only offered if the straight line of fire to it is clear of
+// opaque tiles and other zombies, and accuracy falls off with distance.
+
+use crate::{Map, Shade, Zombie};
+use std::collections::HashMap;
+
+/// Fraction of the base hit chance lost at the very edge of a weapon's reach.
+const RANGE_FALLOFF: f32 = 0.5;
+
+/// Tiles on the Bresenham line from `(x0, y0)` to `(x1, y1)`, excluding the
+/// start and including the end.
+pub fn line(x0: i32, y0: i32, x1: i32, y1: i32) -> Vec<(i32, i32)> {
+    let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+    let (sx, sy) = ((x1 - x0).signum(), (y1 - y0).signum());
+    let (mut x, mut y, mut err) = (x0, y0, dx + dy);
+    let mut points = vec![];
+
+    while (x, y) != (x1, y1) {
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
+        }
+        points.push((x, y));
+    }
+    points
+}
+
+pub fn distance(x0: i32, y0: i32, x1: i32, y1: i32) -> f32 {
+    let (dx, dy) = (x1 - x0, y1 - y0);
+    ((dx * dx + dy * dy) as f32).sqrt()
+}
+
+/// Base accuracy scaled down linearly past point-blank range.
+pub fn hit_chance(base: f32, dist: f32, reach: i32) -> f32 {
+    let penalty = RANGE_FALLOFF * (dist - 1.0).max(0.0) / reach.max(1) as f32;
+    base * (1.0 - penalty)
+}
+
+/// Positions of zombies that can be shot from `(px, py)`, nearest first.
+pub fn visible_targets(
+    map: &Map,
+    zombies: &[Zombie],
+    fov: &HashMap<(i32, i32), Shade>,
+    px: i32,
+    py: i32,
+    reach: i32,
+) -> Vec<(i32, i32)> {
+    let mut targets: Vec<(i32, i32)> = zombies
+        .iter()
+        .map(|z| (z.x, z.y))
+        .filter(|pos| fov.contains_key(pos))
+        .filter(|&(x, y)| distance(px, py, x, y) <= reach as f32)
+        .filter(|&(x, y)| {
+            let path = line(px, py, x, y);
+            path[..path.len() - 1]
+                .iter()
+                .all(|&(lx, ly)| !map.blocks_sight(lx, ly) && !zombies.iter().any(|z| (z.x, z.y) == (lx, ly)))
+        })
+        .collect();
+    targets.sort_by(|a, b| distance(px, py, a.0, a.1).total_cmp(&distance(px, py, b.0, b.1)));
+    targets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_ends_on_target() {
+        let points = line(0, 0, 4, 2);
+        assert_eq!(points.len(), 4);
+        assert_eq!(points.last(), Some(&(4, 2)));
+    }
+
+    #[test]
+    fn accuracy_drops_with_distance() {
+        assert_eq!(hit_chance(0.9, 1.0, 8), 0.9);
+        assert!(hit_chance(0.9, 8.0, 8) < hit_chance(0.9, 4.0, 8));
+    }
+}
@@ -0,0 +1,56 @@
+// --- Weapons ---
+//
+// Combat stats for every item that can be held as a weapon. Durability lives
+// in the item registry (it is worn down per stack); everything about how the
+// weapon fights is defined here.
+
+use crate::items::ItemId;
+use crate::noise::NoiseKind;
+
+pub struct WeaponDef {
+    pub item: Option<ItemId>,
+    pub name: &'static str,
+    pub hit_chance: f32,
+    pub damage: (i32, i32),
+    /// How many tiles away a target can be hit from.
+    pub reach: i32,
+    pub noise: NoiseKind,
+    /// Ammunition consumed per attack; `None` for melee weapons.
+    pub ammo: Option<ItemId>,
+}
+
+impl WeaponDef {
+    pub fn is_firearm(&self) -> bool {
+        self.ammo.is_some()
+    }
+}
+
+/// Used whenever nothing is in hand.
+pub static FISTS: WeaponDef = WeaponDef {
+    item: None,
+    name: "Fists",
+    hit_chance: 0.60,
+    damage: (1, 1),
+    reach: 1,
+    noise: NoiseKind::KnifeSwing,
+    ammo: None,
+};
+
+static WEAPONS: &[WeaponDef] = &[
+    WeaponDef { item: Some(ItemId::RustyKnife), name: "Rusty Knife", hit_chance: 0.50, damage: (1, 3), reach: 1, noise: NoiseKind::KnifeSwing, ammo: None },
+    WeaponDef { item: Some(ItemId::BaseballBat), name: "Baseball Bat", hit_chance: 0.65, damage: (3, 6), reach: 1, noise: NoiseKind::BluntImpact, ammo: None },
+    WeaponDef { item: Some(ItemId::Crowbar), name: "Crowbar", hit_chance: 0.70, damage: (3, 5), reach: 1, noise: NoiseKind::BluntImpact, ammo: None },
+    WeaponDef { item: Some(ItemId::FireAxe), name: "Fire Axe", hit_chance: 0.55, damage: (6, 10), reach: 1, noise: NoiseKind::BluntImpact, ammo: None },
+    WeaponDef { item: Some(ItemId::Pistol), name: "Pistol", hit_chance: 0.95, damage: (10, 15), reach: 8, noise: NoiseKind::Gunshot, ammo: Some(ItemId::PistolAmmo) },
+    WeaponDef { item: Some(ItemId::Shotgun), name: "Shotgun", hit_chance: 0.85, damage: (15, 25), reach: 5, noise: NoiseKind::ShotgunBlast, ammo: Some(ItemId::ShotgunShells) },
+];
+
+/// Combat stats for `item`, or `None` if it is not a weapon.
+pub fn weapon_def(item: ItemId) -> Option<&'static WeaponDef> {
+    WEAPONS.iter().find(|w| w.item == Some(item))
+}
+
+/// Stats for whatever is in hand, falling back to bare fists.
+pub fn held(equipped: Option<ItemId>) -> &'static WeaponDef {
+    equipped.and_then(weapon_def).unwrap_or(&FISTS)
+}
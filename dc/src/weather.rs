@@ -0,0 +1,142 @@
+// --- Weather ---
+//
+// A small Markov chain: each weather spell lasts a random number of turns and
+// then rolls the next one from a per-weather transition table. Weather bends
+// how far the player sees, how fast thirst drains and how far noise carries.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Shortest and longest a weather spell lasts, in turns.
+const SPELL_TURNS: (u32, u32) = (30, 90);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Weather {
+    Clear,
+    Rain,
+    Fog,
+    Heatwave,
+    Storm,
+}
+
+impl Weather {
+    pub const ALL: [Weather; 5] = [Weather::Clear, Weather::Rain, Weather::Fog, Weather::Heatwave, Weather::Storm];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Weather::Clear => "Clear",
+            Weather::Rain => "Rain",
+            Weather::Fog => "Fog",
+            Weather::Heatwave => "Heatwave",
+            Weather::Storm => "Storm",
+        }
+    }
+
+    pub fn is_raining(self) -> bool {
+        matches!(self, Weather::Rain | Weather::Storm)
+    }
+
+    /// How far the player can see through this weather, given the clear-sky radius.
+    pub fn view_radius(self, base: i32) -> i32 {
+        let radius = match self {
+            Weather::Clear | Weather::Heatwave => base,
+            Weather::Rain => base - 2,
+            Weather::Storm => base - 4,
+            Weather::Fog => base / 3,
+        };
+        radius.max(2)
+    }
+
+    /// Turns between each point of thirst lost.
+    pub fn thirst_interval(self) -> u32 {
+        match self {
+            Weather::Clear | Weather::Fog => 5,
+            Weather::Rain | Weather::Storm => 7,
+            Weather::Heatwave => 2,
+        }
+    }
+
+    /// How far a noise of the given radius carries; rain and wind drown sound out.
+    pub fn noise_radius(self, radius: i32) -> i32 {
+        match self {
+            Weather::Clear | Weather::Fog | Weather::Heatwave => radius,
+            Weather::Rain => (radius * 3 / 4).max(1),
+            Weather::Storm => (radius / 2).max(1),
+        }
+    }
+
+    /// Relative odds of each weather following this one, in `ALL` order.
+    fn transitions(self) -> [u32; 5] {
+        match self {
+            Weather::Clear => [3, 3, 2, 2, 0],
+            Weather::Rain => [4, 2, 2, 0, 2],
+            Weather::Fog => [5, 3, 1, 0, 0],
+            Weather::Heatwave => [5, 0, 0, 2, 2],
+            Weather::Storm => [3, 5, 0, 0, 1],
+        }
+    }
+
+    fn roll_next(self, rng: &mut impl Rng) -> Self {
+        let weights = self.transitions();
+        let mut pick = rng.gen_range(0..weights.iter().sum::<u32>());
+        for (weather, weight) in Self::ALL.into_iter().zip(weights) {
+            if pick < weight {
+                return weather;
+            }
+            pick -= weight;
+        }
+        Weather::Clear
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct WeatherState {
+    pub current: Weather,
+    /// Turns until the next weather roll.
+    pub turns_left: u32,
+}
+
+impl Default for WeatherState {
+    fn default() -> Self {
+        WeatherState { current: Weather::Clear, turns_left: SPELL_TURNS.0 }
+    }
+}
+
+impl WeatherState {
+    /// Advances one turn; returns the new weather if it just changed.
+    pub fn tick(&mut self, rng: &mut impl Rng) -> Option<Weather> {
+        self.turns_left = self.turns_left.saturating_sub(1);
+        if self.turns_left > 0 {
+            return None;
+        }
+        self.turns_left = rng.gen_range(SPELL_TURNS.0..=SPELL_TURNS.1);
+        let next = self.current.roll_next(rng);
+        let changed = next != self.current;
+        self.current = next;
+        changed.then_some(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn weather_changes_only_when_the_spell_ends() {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let mut weather = WeatherState { current: Weather::Fog, turns_left: 3 };
+        assert_eq!(weather.tick(&mut rng), None);
+        assert_eq!(weather.tick(&mut rng), None);
+        weather.tick(&mut rng);
+        assert!(weather.turns_left >= SPELL_TURNS.0);
+    }
+
+    #[test]
+    fn storms_muffle_noise_and_fog_hides_everything() {
+        assert!(Weather::Storm.noise_radius(20) < Weather::Clear.noise_radius(20));
+        assert!(Weather::Fog.view_radius(12) < Weather::Rain.view_radius(12));
+        assert!(Weather::Heatwave.thirst_interval() < Weather::Clear.thirst_interval());
+    }
+}
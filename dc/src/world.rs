@@ -0,0 +1,75 @@
+// --- World ---
+//
+// The world is a stack of maps sharing one coordinate space: level 0 is the
+// street, level 1 holds the upper floors of houses and the mall roof. Stairs
+// sit at the same (x, y) on both levels. Only the player's level is live; the
+// zombies on every other level are parked here until the player returns.
+
+use crate::mapgen::NotePlacement;
+use crate::{Map, Zombie};
+use serde::{Deserialize, Serialize};
+
+pub const STREET_LEVEL: usize = 0;
+pub const UPPER_LEVEL: usize = 1;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct World {
+    pub levels: Vec<Map>,
+    /// Zombies waiting on each level while the player is elsewhere.
+    parked: Vec<Vec<Zombie>>,
+    /// Where the street-level notes were put, for the diagnostics view.
+    pub notes: Vec<NotePlacement>,
+}
+
+impl World {
+    pub fn new(levels: Vec<Map>) -> Self {
+        let parked = vec![vec![]; levels.len()];
+        World { levels, parked, notes: vec![] }
+    }
+
+    pub fn park(&mut self, level: usize, zombies: Vec<Zombie>) {
+        self.parked[level].extend(zombies);
+    }
+
+    /// Wakes the zombies left on `level`, handing them back to the caller.
+    pub fn unpark(&mut self, level: usize) -> Vec<Zombie> {
+        std::mem::take(&mut self.parked[level])
+    }
+
+    pub fn level_name(level: usize) -> &'static str {
+        match level {
+            STREET_LEVEL => "Street",
+            _ => "Upstairs",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{use_stairs, Awareness, GameState, ZombieKind};
+
+    #[test]
+    fn alerted_zombies_follow_up_the_stairs() {
+        let ground = Map::from_ascii(&["......", "..<...", "......"]);
+        let upper = Map::from_ascii(&["######", "#.>..#", "######"]);
+        let mut world = World::new(vec![ground, upper]);
+        let mut state = GameState::new(0);
+        (state.player_x, state.player_y) = (2, 1);
+
+        let mut chaser = Zombie::new(3, 1, ZombieKind::Shambler);
+        chaser.awareness = Awareness::Chasing { x: 2, y: 1 };
+        let idle = Zombie::new(1, 0, ZombieKind::Shambler);
+        state.zombies = vec![chaser, idle];
+
+        assert_eq!(use_stairs(&mut world, &mut state, false), 0, "no way down from the street");
+        assert_eq!(use_stairs(&mut world, &mut state, true), 1);
+        assert_eq!(state.level, UPPER_LEVEL);
+        assert_eq!(state.zombies.len(), 1, "only the chasing zombie follows");
+        assert!(world.levels[UPPER_LEVEL].is_walkable(state.zombies[0].x, state.zombies[0].y));
+
+        assert_eq!(use_stairs(&mut world, &mut state, false), 1);
+        assert_eq!(state.level, STREET_LEVEL);
+        assert_eq!(state.zombies.len(), 2, "the idle one was waiting downstairs");
+    }
+}